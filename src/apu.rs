@@ -0,0 +1,66 @@
+use crate::MemoryAddressError;
+
+const NR52: u16 = 0xFF26;
+const NR52_POWER: u8 = 0b1000_0000;
+
+// Bits that always read back as 1 for NR10 (0xFF10) through NR52 (0xFF26).
+// Write-only bits and unused registers read as 1
+const READ_MASKS: [u8; 0x17] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // unused, NR21-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // unused, NR41-NR44
+    0x00, 0x00, 0x70, // NR50-NR52
+];
+
+// Sound registers and wave RAM. No audio is generated yet, this only keeps
+// the registers behaving like hardware for games that read them back
+#[derive(Clone)]
+pub struct Apu {
+    registers: [u8; 0x17],
+    wave_ram: [u8; 0x10],
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 0x17],
+            wave_ram: [0; 0x10],
+        }
+    }
+
+    fn powered(&self) -> bool {
+        self.registers[(NR52 - 0xFF10) as usize] & NR52_POWER != 0
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        Ok(match address {
+            0xFF10..=0xFF26 => {
+                let index = (address - 0xFF10) as usize;
+                self.registers[index] | READ_MASKS[index]
+            },
+            0xFF27..=0xFF2F => 0xFF,
+            0xFF30..=0xFF3F => self.wave_ram[(address - 0xFF30) as usize],
+            _ => return Err(MemoryAddressError),
+        })
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            NR52 => {
+                // Only the power bit is writable. Powering off clears every register
+                if value & NR52_POWER == 0 {
+                    self.registers = [0; 0x17];
+                }
+                self.registers[(NR52 - 0xFF10) as usize] = value & NR52_POWER;
+            },
+            // Registers ignore writes while the APU is powered off
+            0xFF10..=0xFF25 if !self.powered() => {},
+            0xFF10..=0xFF25 => self.registers[(address - 0xFF10) as usize] = value,
+            0xFF27..=0xFF2F => {},
+            0xFF30..=0xFF3F => self.wave_ram[(address - 0xFF30) as usize] = value,
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
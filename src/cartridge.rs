@@ -0,0 +1,364 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::mapper::{Mapper, Mbc1, Mbc2, Mbc3, Mbc5, RomOnly, RumbleEvent};
+use crate::MemoryAddressError;
+use thiserror::Error;
+
+const HEADER_END: usize = 0x0150;
+// How often dirty battery RAM is flushed while running: once per emulated second
+const SAVE_INTERVAL_CYCLES: i32 = 4_194_304;
+
+#[derive(Debug, Error)]
+pub enum CartridgeError {
+    #[error("Failed to read ROM file")]
+    Io(#[from] io::Error),
+    #[error("Failed to read save file")]
+    Save(#[source] io::Error),
+    #[error("ROM is {0} bytes, too small to contain a cartridge header")]
+    TooSmall(usize),
+    #[error("Unknown cartridge type {0:#04X}")]
+    UnknownCartridgeType(u8),
+    #[error("Unknown ROM size code {0:#04X}")]
+    UnknownRomSize(u8),
+    #[error("Unknown RAM size code {0:#04X}")]
+    UnknownRamSize(u8),
+    #[error("ROM is {actual} bytes but the header declares {expected} bytes")]
+    RomSizeMismatch { expected: usize, actual: usize },
+    #[error("Header checksum is {expected:#04X} but the header sums to {computed:#04X}")]
+    HeaderChecksum { expected: u8, computed: u8 },
+    #[error("Global checksum is {expected:#06X} but the ROM sums to {computed:#06X}")]
+    GlobalChecksum { expected: u16, computed: u16 },
+    #[error("{0:?} cartridges are not supported")]
+    UnsupportedMapper(MapperKind),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CgbSupport {
+    Dmg,
+    Enhanced,
+    Only,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MapperKind {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
+    Tama5,
+    HuC3,
+    HuC1,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CartridgeType {
+    pub code: u8,
+    pub mapper: MapperKind,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Licensee {
+    Old(u8),
+    New(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub cgb: CgbSupport,
+    pub sgb: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+#[derive(Clone)]
+pub struct Cartridge {
+    pub header: CartridgeHeader,
+    mapper: Box<dyn Mapper>,
+    // Only set for battery-backed cartridges
+    save_path: Option<PathBuf>,
+    ram_dirty: bool,
+    save_requested: bool,
+    cycles_since_save: i32,
+}
+
+impl CartridgeType {
+    fn from_code(code: u8) -> Result<Self, CartridgeError> {
+        use MapperKind::*;
+        let (mapper, ram, battery, timer, rumble) = match code {
+            0x00 => (RomOnly, false, false, false, false),
+            0x01 => (Mbc1, false, false, false, false),
+            0x02 => (Mbc1, true, false, false, false),
+            0x03 => (Mbc1, true, true, false, false),
+            0x05 => (Mbc2, false, false, false, false),
+            0x06 => (Mbc2, false, true, false, false),
+            0x08 => (RomOnly, true, false, false, false),
+            0x09 => (RomOnly, true, true, false, false),
+            0x0B => (Mmm01, false, false, false, false),
+            0x0C => (Mmm01, true, false, false, false),
+            0x0D => (Mmm01, true, true, false, false),
+            0x0F => (Mbc3, false, true, true, false),
+            0x10 => (Mbc3, true, true, true, false),
+            0x11 => (Mbc3, false, false, false, false),
+            0x12 => (Mbc3, true, false, false, false),
+            0x13 => (Mbc3, true, true, false, false),
+            0x19 => (Mbc5, false, false, false, false),
+            0x1A => (Mbc5, true, false, false, false),
+            0x1B => (Mbc5, true, true, false, false),
+            0x1C => (Mbc5, false, false, false, true),
+            0x1D => (Mbc5, true, false, false, true),
+            0x1E => (Mbc5, true, true, false, true),
+            0x20 => (Mbc6, false, false, false, false),
+            0x22 => (Mbc7, true, true, false, true),
+            0xFC => (PocketCamera, false, false, false, false),
+            0xFD => (Tama5, false, false, false, false),
+            0xFE => (HuC3, false, false, false, false),
+            0xFF => (HuC1, true, true, false, false),
+            _ => return Err(CartridgeError::UnknownCartridgeType(code)),
+        };
+        Ok(Self { code, mapper, ram, battery, timer, rumble })
+    }
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::TooSmall(rom.len()));
+        }
+        let cgb = match rom[0x0143] {
+            0x80 => CgbSupport::Enhanced,
+            0xC0 => CgbSupport::Only,
+            _ => CgbSupport::Dmg,
+        };
+        // CGB cartridges reuse the end of the title for the manufacturer code and CGB flag
+        let title_bytes = match cgb {
+            CgbSupport::Dmg => &rom[0x0134..=0x0143],
+            _ => &rom[0x0134..=0x013E],
+        };
+        let title = title_bytes
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect::<String>()
+            .trim_end()
+            .to_string();
+        let licensee = match rom[0x014B] {
+            0x33 => Licensee::New(String::from_utf8_lossy(&rom[0x0144..=0x0145]).into_owned()),
+            code => Licensee::Old(code),
+        };
+        let header = Self {
+            title,
+            cgb,
+            sgb: rom[0x0146] == 0x03,
+            cartridge_type: CartridgeType::from_code(rom[0x0147])?,
+            rom_size_code: rom[0x0148],
+            ram_size_code: rom[0x0149],
+            licensee,
+            version: rom[0x014C],
+            header_checksum: rom[0x014D],
+            global_checksum: u16::from_be_bytes([rom[0x014E], rom[0x014F]]),
+        };
+        header.rom_size()?;
+        header.ram_size()?;
+
+        let computed = Self::compute_header_checksum(rom);
+        if computed != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksum { expected: header.header_checksum, computed });
+        }
+        Ok(header)
+    }
+
+    pub fn rom_size(&self) -> Result<usize, CartridgeError> {
+        match self.rom_size_code {
+            0x00..=0x08 => Ok(0x8000 << self.rom_size_code),
+            code => Err(CartridgeError::UnknownRomSize(code)),
+        }
+    }
+
+    pub fn ram_size(&self) -> Result<usize, CartridgeError> {
+        Ok(match self.ram_size_code {
+            0x00 => 0,
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            code => return Err(CartridgeError::UnknownRamSize(code)),
+        })
+    }
+
+    pub fn compute_header_checksum(rom: &[u8]) -> u8 {
+        rom[0x0134..=0x014C]
+            .iter()
+            .fold(0u8, |acc, &b| acc.wrapping_sub(b).wrapping_sub(1))
+    }
+
+    // Sum of every byte in the ROM except the two checksum bytes themselves
+    pub fn compute_global_checksum(rom: &[u8]) -> u16 {
+        rom.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 0x014E && *i != 0x014F)
+            .fold(0u16, |acc, (_, &b)| acc.wrapping_add(b as u16))
+    }
+}
+
+impl Cartridge {
+    // Loads a ROM, and for battery-backed carts the .sav file next to it
+    pub fn load(path: &Path) -> Result<Self, CartridgeError> {
+        let mut cartridge = Self::from_bytes(fs::read(path)?)?;
+        if cartridge.header.cartridge_type.battery {
+            let save_path = path.with_extension("sav");
+            cartridge.load_save(&save_path).map_err(CartridgeError::Save)?;
+            cartridge.save_path = Some(save_path);
+        }
+        Ok(cartridge)
+    }
+
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        let header = CartridgeHeader::parse(&rom)?;
+        let expected = header.rom_size()?;
+        if rom.len() != expected {
+            return Err(CartridgeError::RomSizeMismatch { expected, actual: rom.len() });
+        }
+        let computed = CartridgeHeader::compute_global_checksum(&rom);
+        if computed != header.global_checksum {
+            return Err(CartridgeError::GlobalChecksum { expected: header.global_checksum, computed });
+        }
+        let ram_size = header.ram_size()?;
+        let mapper: Box<dyn Mapper> = match header.cartridge_type.mapper {
+            MapperKind::RomOnly => Box::new(RomOnly::new(rom, ram_size)),
+            MapperKind::Mbc1 => Box::new(Mbc1::new(rom, ram_size)),
+            MapperKind::Mbc2 => Box::new(Mbc2::new(rom)),
+            MapperKind::Mbc3 => Box::new(Mbc3::new(rom, ram_size, header.cartridge_type.timer)),
+            MapperKind::Mbc5 => Box::new(Mbc5::new(rom, ram_size, header.cartridge_type.rumble)),
+            kind => return Err(CartridgeError::UnsupportedMapper(kind)),
+        };
+        Ok(Self {
+            header,
+            mapper,
+            save_path: None,
+            ram_dirty: false,
+            save_requested: false,
+            cycles_since_save: 0,
+        })
+    }
+
+    fn load_save(&mut self, save_path: &Path) -> io::Result<()> {
+        let data = match fs::read(save_path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let ram = self.mapper.ram_mut();
+        let ram_len = ram.len().min(data.len());
+        ram[..ram_len].copy_from_slice(&data[..ram_len]);
+        if let (Some(rtc), Some(footer)) = (self.mapper.rtc_mut(), data.get(ram_len..)) {
+            if let Ok(footer) = footer.try_into() {
+                rtc.load(footer);
+            }
+        }
+        Ok(())
+    }
+
+    // Writes battery RAM and RTC state out to the .sav file. The file is
+    // replaced atomically so a crash mid-write can't corrupt an older save
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some(save_path) = &self.save_path else {
+            return Ok(());
+        };
+        let mut data = self.mapper.ram().to_vec();
+        if let Some(rtc) = self.mapper.rtc_mut() {
+            data.extend_from_slice(&rtc.save());
+        }
+        let temp_path = save_path.with_extension("sav.tmp");
+        fs::write(&temp_path, &data)?;
+        fs::rename(&temp_path, save_path)?;
+        self.ram_dirty = false;
+        self.save_requested = false;
+        self.cycles_since_save = 0;
+        Ok(())
+    }
+
+    // Whether battery RAM has changed and should be flushed now, either
+    // because the game disabled RAM or because the periodic interval passed
+    pub fn save_due(&self) -> bool {
+        self.save_path.is_some() && self.ram_dirty
+            && (self.save_requested || self.cycles_since_save >= SAVE_INTERVAL_CYCLES)
+    }
+
+    pub fn step(&mut self, cycles: i32) {
+        self.mapper.step(cycles);
+        self.cycles_since_save = self.cycles_since_save.saturating_add(cycles);
+    }
+
+    pub fn sync_rtc_to_host(&mut self) {
+        if let Some(rtc) = self.mapper.rtc_mut() {
+            rtc.sync_to_host();
+        }
+    }
+
+    // Power cycling resets the mapper, flushing anything written while RAM
+    // was still enabled
+    pub fn reset(&mut self) {
+        if self.mapper.ram_enabled() {
+            self.save_requested = true;
+        }
+        self.mapper.reset();
+    }
+
+    pub fn poll_rumble(&mut self) -> Option<RumbleEvent> {
+        self.mapper.poll_rumble()
+    }
+
+    // Whether 0xA000-0xBFFF is currently backed by RAM or RTC registers
+    pub fn ram_accessible(&self) -> bool {
+        let has_ram = !self.mapper.ram().is_empty() || self.header.cartridge_type.timer;
+        has_ram && self.mapper.ram_enabled()
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        match address {
+            0x0000..=0x7FFF => Ok(self.mapper.read_rom(address)),
+            0xA000..=0xBFFF => Ok(self.mapper.read_ram(address)),
+            _ => Err(MemoryAddressError),
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            0x0000..=0x7FFF => {
+                let was_enabled = self.mapper.ram_enabled();
+                self.mapper.write_register(address, value);
+                // Games disable RAM once they're done writing a save
+                if was_enabled && !self.mapper.ram_enabled() {
+                    self.save_requested = true;
+                }
+            },
+            0xA000..=0xBFFF => {
+                // Writes with RAM disabled are dropped and have nothing to save
+                if self.ram_accessible() {
+                    self.ram_dirty = true;
+                }
+                self.mapper.write_ram(address, value);
+            },
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::joypad::Button;
+use crate::palette::{Colors, Palette};
+use minifb::Key;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse config file")]
+    Parse(#[from] toml::de::Error),
+    #[error("Unknown button \"{0}\"")]
+    UnknownButton(String),
+    #[error("Unknown hotkey \"{0}\"")]
+    UnknownHotkey(String),
+    #[error("Unknown key \"{0}\"")]
+    UnknownKey(String),
+    #[error("Unknown palette preset \"{0}\"")]
+    UnknownPalette(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Pause,
+    FastForward,
+    Screenshot,
+    SaveState,
+    LoadState,
+    Reset,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Button(Button),
+    Hotkey(Hotkey),
+}
+
+// Any number of keys can be bound to the same action
+const DEFAULT_BINDINGS: [(Key, Action); 14] = [
+    (Key::Right, Action::Button(Button::Right)),
+    (Key::Left, Action::Button(Button::Left)),
+    (Key::Up, Action::Button(Button::Up)),
+    (Key::Down, Action::Button(Button::Down)),
+    (Key::X, Action::Button(Button::A)),
+    (Key::Z, Action::Button(Button::B)),
+    (Key::Backspace, Action::Button(Button::Select)),
+    (Key::Enter, Action::Button(Button::Start)),
+    (Key::P, Action::Hotkey(Hotkey::Pause)),
+    (Key::Tab, Action::Hotkey(Hotkey::FastForward)),
+    (Key::F12, Action::Hotkey(Hotkey::Screenshot)),
+    (Key::F5, Action::Hotkey(Hotkey::SaveState)),
+    (Key::F8, Action::Hotkey(Hotkey::LoadState)),
+    (Key::F1, Action::Hotkey(Hotkey::Reset)),
+];
+
+// Every key minifb reports, for looking keys up by name
+const KEYS: [Key; 106] = [
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+    Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal,
+    Key::LeftBracket, Key::Minus, Key::Period, Key::RightBracket,
+    Key::Semicolon, Key::Slash,
+    Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape,
+    Key::Home, Key::Insert, Key::Menu, Key::PageDown, Key::PageUp,
+    Key::Pause, Key::Space, Key::Tab, Key::NumLock, Key::CapsLock,
+    Key::ScrollLock, Key::LeftShift, Key::RightShift, Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4,
+    Key::NumPad5, Key::NumPad6, Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    Key::NumPadDot, Key::NumPadSlash, Key::NumPadAsterisk, Key::NumPadMinus,
+    Key::NumPadPlus, Key::NumPadEnter,
+    Key::LeftAlt, Key::RightAlt, Key::LeftSuper, Key::RightSuper,
+];
+
+// The file as written, e.g.
+//
+//   [buttons]
+//   a = ["X", "K"]
+//   start = ["Enter", "Space"]
+//
+//   [hotkeys]
+//   fast_forward = ["Tab"]
+//
+//   [palette]
+//   preset = "green"
+//   obj1 = [0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000]
+//
+// Each entry replaces the default keys for that action and takes its keys
+// away from any other action, anything left out keeps its defaults. Palette
+// layers given as RGB quadruples, lightest shade first, override the preset
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    buttons: BTreeMap<String, Vec<String>>,
+    hotkeys: BTreeMap<String, Vec<String>>,
+    palette: PaletteFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PaletteFile {
+    preset: Option<String>,
+    bg: Option<Colors>,
+    obj0: Option<Colors>,
+    obj1: Option<Colors>,
+}
+
+pub struct Config {
+    pub bindings: Vec<(Key, Action)>,
+    pub palette: Palette,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS.to_vec(),
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let file: ConfigFile = toml::from_str(text)?;
+        let mut config = Self::default();
+        for (name, keys) in &file.buttons {
+            let button = parse_button(name).ok_or_else(|| ConfigError::UnknownButton(name.clone()))?;
+            config.rebind(Action::Button(button), keys)?;
+        }
+        for (name, keys) in &file.hotkeys {
+            let hotkey = parse_hotkey(name).ok_or_else(|| ConfigError::UnknownHotkey(name.clone()))?;
+            config.rebind(Action::Hotkey(hotkey), keys)?;
+        }
+        if let Some(name) = &file.palette.preset {
+            config.palette = Palette::preset(name).ok_or_else(|| ConfigError::UnknownPalette(name.clone()))?;
+        }
+        if let Some(colors) = file.palette.bg {
+            config.palette.bg = colors;
+        }
+        if let Some(colors) = file.palette.obj0 {
+            config.palette.obj0 = colors;
+        }
+        if let Some(colors) = file.palette.obj1 {
+            config.palette.obj1 = colors;
+        }
+        Ok(config)
+    }
+
+    // A rebound key stops doing whatever it did before
+    fn rebind(&mut self, action: Action, keys: &[String]) -> Result<(), ConfigError> {
+        self.bindings.retain(|&(_, bound)| bound != action);
+        for name in keys {
+            let key = parse_key(name).ok_or_else(|| ConfigError::UnknownKey(name.clone()))?;
+            self.bindings.retain(|&(bound, _)| bound != key);
+            self.bindings.push((key, action));
+        }
+        Ok(())
+    }
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "right" => Button::Right,
+        "left" => Button::Left,
+        "up" => Button::Up,
+        "down" => Button::Down,
+        "a" => Button::A,
+        "b" => Button::B,
+        "select" => Button::Select,
+        "start" => Button::Start,
+        _ => return None,
+    })
+}
+
+fn parse_hotkey(name: &str) -> Option<Hotkey> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "pause" => Hotkey::Pause,
+        "fast_forward" => Hotkey::FastForward,
+        "screenshot" => Hotkey::Screenshot,
+        "save_state" => Hotkey::SaveState,
+        "load_state" => Hotkey::LoadState,
+        "reset" => Hotkey::Reset,
+        _ => return None,
+    })
+}
+
+// Keys go by their minifb names, ignoring case. Digits can be written
+// without the "Key" prefix
+fn parse_key(name: &str) -> Option<Key> {
+    let name = if name.len() == 1 && name.as_bytes()[0].is_ascii_digit() {
+        format!("Key{}", name)
+    } else {
+        name.to_string()
+    };
+    KEYS.iter().copied().find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions(config: &Config, key: Key) -> Vec<Action> {
+        config.bindings.iter().filter(|&&(bound, _)| bound == key).map(|&(_, action)| action).collect()
+    }
+
+    #[test]
+    fn rebound_key_loses_default_action() {
+        let config = Config::parse("[buttons]\na = [\"Z\"]\n\n[hotkeys]\npause = [\"Enter\"]\n").unwrap();
+        assert_eq!(actions(&config, Key::Z), [Action::Button(Button::A)]);
+        assert_eq!(actions(&config, Key::Enter), [Action::Hotkey(Hotkey::Pause)]);
+        // The old keys are free, and Start is left without one
+        assert!(actions(&config, Key::X).is_empty());
+        assert!(actions(&config, Key::P).is_empty());
+        assert!(!config.bindings.iter().any(|&(_, action)| action == Action::Button(Button::Start)));
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Cpu {
     pub a: u8,
     pub b: u8,
@@ -11,14 +11,33 @@ pub struct Cpu {
     pub pc: u16,
     pub sp: u16,
     pub ime: bool,
+    pub ime_delay: ImeDelay,
+    pub halted: bool,
+    pub halt_bug: bool,
+    pub stopped: bool,
+    pub double_speed: bool,
+    pub speed_switch_armed: bool,
 }
 
+// EI only sets IME once the instruction following it has completed
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImeDelay {
+    #[default]
+    Idle,
+    // EI has just executed
+    Scheduled,
+    // The instruction after EI is executing
+    Pending,
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum Register {
     Register8(Register8),
     Register16(Register16),
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum Register8 {
     A,
@@ -31,6 +50,7 @@ pub enum Register8 {
     L,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum Register16 {
     AF,
@@ -49,6 +69,7 @@ pub enum Flag {
     C,
 }
 
+#[allow(dead_code)]
 pub struct InvalidRegisterOperation;
 
 pub fn split_word(word: u16) -> (u8, u8) {
@@ -110,6 +131,45 @@ impl Cpu {
         }
     }
 
+    pub fn schedule_ime(&mut self) {
+        // A second EI doesn't restart a delay that is already running
+        if !self.ime && self.ime_delay == ImeDelay::Idle {
+            self.ime_delay = ImeDelay::Scheduled;
+        }
+    }
+
+    pub fn disable_ime(&mut self) {
+        self.ime = false;
+        self.ime_delay = ImeDelay::Idle;
+    }
+
+    pub fn enable_ime(&mut self) {
+        self.ime = true;
+        self.ime_delay = ImeDelay::Idle;
+    }
+
+    // Advances the EI delay. Called once after every executed instruction
+    pub fn tick_ime(&mut self) {
+        self.ime_delay = match self.ime_delay {
+            ImeDelay::Idle => ImeDelay::Idle,
+            ImeDelay::Scheduled => ImeDelay::Pending,
+            ImeDelay::Pending => {
+                self.ime = true;
+                ImeDelay::Idle
+            }
+        };
+    }
+
+    // KEY1 (0xFF4D), CGB only. Bit 7 is the current speed, bit 0 arms a
+    // speed switch on the next STOP
+    pub fn read_key1(&self) -> u8 {
+        0x7E | ((self.double_speed as u8) << 7) | self.speed_switch_armed as u8
+    }
+
+    pub fn write_key1(&mut self, value: u8) {
+        self.speed_switch_armed = value & 1 != 0;
+    }
+
     pub fn has_half_carry(a: u8, b: u8) -> bool {
         ((a & 0xF) + (b & 0xF)) > 0xF
     }
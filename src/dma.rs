@@ -0,0 +1,68 @@
+use crate::MemoryAddressError;
+
+const OAM_SIZE: u8 = 0xA0;
+
+// OAM DMA (0xFF46). Writing XX copies XX00-XX9F into OAM, one byte per
+// M-cycle. While it runs the CPU can only use HRAM
+#[derive(Clone)]
+pub struct Dma {
+    source: u8,
+    // Index of the next byte to copy, None when no transfer is running
+    progress: Option<u8>,
+    // The byte on the bus during the last M-cycle. CPU reads outside HRAM
+    // see this instead of memory
+    conflict_value: u8,
+}
+
+impl Dma {
+    pub fn new() -> Self {
+        Self {
+            source: 0,
+            progress: None,
+            conflict_value: 0xFF,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    pub fn conflict_value(&self) -> u8 {
+        self.conflict_value
+    }
+
+    // The source address and OAM index of the byte to copy this M-cycle
+    pub fn next_transfer(&mut self) -> Option<(u16, usize)> {
+        let index = self.progress?;
+        self.progress = (index + 1 < OAM_SIZE).then_some(index + 1);
+        // Sources past 0xDF00 read the echo of WRAM
+        let mut address = u16::from_be_bytes([self.source, index]);
+        if address >= 0xE000 {
+            address -= 0x2000;
+        }
+        Some((address, index as usize))
+    }
+
+    pub fn set_conflict_value(&mut self, value: u8) {
+        self.conflict_value = value;
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        match address {
+            0xFF46 => Ok(self.source),
+            _ => Err(MemoryAddressError),
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            // Starting a new transfer restarts from the first byte
+            0xFF46 => {
+                self.source = value;
+                self.progress = Some(0);
+            },
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
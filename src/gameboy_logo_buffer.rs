@@ -1,4 +1,5 @@
-pub const GAMEBOY_LOGO_SCREEN: [u8; 160 * 144] = [
+#[allow(dead_code)]
+pub static GAMEBOY_LOGO_SCREEN: [u8; 160 * 144] = [
 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170,
 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170,
 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170, 170,
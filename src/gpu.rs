@@ -1,23 +1,98 @@
+use std::cell::Cell;
+
 use crate::MemoryAddressError;
+use crate::interrupts::{Interrupt, Interrupts};
 use itertools::Itertools;
 
+mod fifo;
+
+use fifo::PixelFifo;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+// Length of one frame in cycles, 154 lines of 456
+const FRAME_CYCLES: i32 = 70224;
+
+const LCDC_BG_ENABLE: u8 = 0b0000_0001;
+const LCDC_OBJ_ENABLE: u8 = 0b0000_0010;
+const LCDC_OBJ_SIZE: u8 = 0b0000_0100;
+const LCDC_BG_MAP: u8 = 0b0000_1000;
+const LCDC_TILE_DATA: u8 = 0b0001_0000;
+const LCDC_WINDOW_ENABLE: u8 = 0b0010_0000;
+const LCDC_WINDOW_MAP: u8 = 0b0100_0000;
+const LCDC_ENABLE: u8 = 0b1000_0000;
+
+const STAT_COINCIDENCE: u8 = 0b0000_0100;
+const STAT_HBLANK_INTERRUPT: u8 = 0b0000_1000;
+const STAT_VBLANK_INTERRUPT: u8 = 0b0001_0000;
+const STAT_OAM_INTERRUPT: u8 = 0b0010_0000;
+const STAT_COINCIDENCE_INTERRUPT: u8 = 0b0100_0000;
+
+const OBJ_PALETTE: u8 = 0b0001_0000;
+const OBJ_X_FLIP: u8 = 0b0010_0000;
+const OBJ_Y_FLIP: u8 = 0b0100_0000;
+const OBJ_BEHIND_BG: u8 = 0b1000_0000;
+const SPRITES_PER_LINE: usize = 10;
+// Tile data covers 0x8000-0x97FF, the rest of VRAM holds the tile maps
+const TILE_COUNT: usize = 384;
+
+// How mode 3 turns into pixels. The scanline renderer draws each line in one
+// go with fixed mode lengths, the FIFO renderer runs the fetchers dot by dot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Renderer {
+    Scanline,
+    Fifo,
+}
 
+#[derive(Clone)]
 pub struct Gpu {
     pub gpu_mode: GpuMode,
+    pub renderer: Renderer,
     cycles: i32,
-    line: u8,
-    pub vram: [u8; 0x2000],
+    // Mode 3 and mode 0 share 376 dots, so HBlank is shorter when drawing
+    // took longer
+    hblank_cycles: i32,
+    fifo: PixelFifo,
+    // Only written through `write`, which keeps `tiles` in sync
+    vram: [u8; 0x2000],
     oam: [u8; 0xA0],
-    pub tiles: Vec<Tile>,
+    // CPU accesses dropped because the PPU was using VRAM or OAM. Reads
+    // count too, hence the Cell
+    blocked_accesses: Cell<u64>,
+    // Decoded tile data, refreshed from VRAM before each line is drawn
+    tiles: Vec<Tile>,
+    // Tiles written since they were last decoded
+    dirty_tiles: [bool; TILE_COUNT],
     lcdc: u8,
     stat: u8,
+    // The OR of all enabled STAT interrupt sources. The interrupt is only
+    // requested when this goes high, so overlapping sources block each other
+    stat_line: bool,
     scroll_x: u8,
     scroll_y: u8,
     ly: u8,
     lyc: u8,
     pallettes: [u8; 3],
     window_x_y: [u8; 2],
-    frame_buffer: [u8; 160 * 144] // 160x144 screen resolution
+    // Set once LY has matched WY this frame, the window can't show before that
+    window_triggered: bool,
+    // The window's own line counter. It only advances on lines where the
+    // window was drawn, so hiding it mid-frame doesn't skip any of its rows
+    window_line: u8,
+    vram_bank: u8,
+    hdma: [u8; 5],
+    // OAM indices of the sprites picked by the OAM scan for this line
+    line_sprites: Vec<usize>,
+    // Colour indices of the background and window on this line, before the
+    // palette. Sprites behind the background only show over index 0
+    bg_indices: [u8; SCREEN_WIDTH],
+    // Shades after the palettes have been applied
+    frame_buffer: [Pixel; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // Set on entering VBlank, cleared once the frontend picks up the frame
+    frame_ready: bool,
+    // The first frame after turning the LCD on isn't shown, the screen stays
+    // blank until the next one
+    skip_frame: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -33,6 +108,47 @@ pub enum Color {
     White
 }
 
+// Which palette register a pixel went through, so the frontend can colour
+// each one differently
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Bg,
+    Obj0,
+    Obj1,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Pixel {
+    pub color: Color,
+    pub layer: Layer,
+}
+
+impl Pixel {
+    const BLANK: Self = Self { color: Color::White, layer: Layer::Bg };
+}
+
+impl Color {
+    // Looks up a 2-bit colour index in a palette register like BGP
+    fn from_palette(palette: u8, index: u8) -> Self {
+        match (palette >> (index * 2)) & 0x03 {
+            0 => Color::White,
+            1 => Color::LGray,
+            2 => Color::DGray,
+            _ => Color::Black,
+        }
+    }
+
+    // Tiles store their raw 2-bit colour indices as the matching shade
+    fn index(self) -> u8 {
+        match self {
+            Color::White => 0,
+            Color::LGray => 1,
+            Color::DGray => 2,
+            Color::Black => 3,
+        }
+    }
+}
+
 impl Tile {
     fn new_blank() -> Self {
         Self {
@@ -41,6 +157,7 @@ impl Tile {
     }
 }
 
+#[derive(Clone)]
 pub enum GpuMode {
     HBlank,
     VBlank,
@@ -48,48 +165,139 @@ pub enum GpuMode {
     Drawing,
 }
 
+impl GpuMode {
+    // The mode number STAT reports in bits 0-1
+    fn bits(&self) -> u8 {
+        match self {
+            GpuMode::HBlank => 0,
+            GpuMode::VBlank => 1,
+            GpuMode::OamScan => 2,
+            GpuMode::Drawing => 3,
+        }
+    }
+}
+
 impl Gpu {
     pub fn new() -> Self {
         Self {
             vram: [0; 0x2000],
             oam: [0; 0xA0],
-            tiles: vec![Tile::new_blank(); 512],
+            blocked_accesses: Cell::new(0),
+            tiles: vec![Tile::new_blank(); TILE_COUNT],
+            dirty_tiles: [false; TILE_COUNT],
             gpu_mode: GpuMode::OamScan,
+            renderer: Renderer::Scanline,
             cycles: 0,
-            line: 0,
-            frame_buffer: [0; 160 * 144],
-            lcdc: 0,
-            stat: 0,
+            hblank_cycles: 204,
+            fifo: PixelFifo::new(),
+            frame_buffer: [Pixel::BLANK; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+            skip_frame: false,
+            line_sprites: Vec::with_capacity(SPRITES_PER_LINE),
+            bg_indices: [0; SCREEN_WIDTH],
+            // The LCD is on and BGP set up by the time the boot ROM hands over
+            lcdc: 0x91,
+            stat: GpuMode::OamScan.bits(),
+            stat_line: false,
             scroll_x: 0,
             scroll_y: 0,
             ly: 0,
             lyc: 0,
-            pallettes: [0; 3],
+            pallettes: [0xFC, 0, 0],
             window_x_y: [0; 2],
-             
+            window_triggered: false,
+            window_line: 0,
+            vram_bank: 0,
+            hdma: [0; 5],
+        }
+    }
+
+    // The PPU owns VRAM during mode 3 and OAM during modes 2 and 3
+    pub fn accessible(&self, address: u16) -> bool {
+        match address {
+            0x8000..=0x9FFF => !matches!(self.gpu_mode, GpuMode::Drawing),
+            0xFE00..=0xFE9F => !matches!(self.gpu_mode, GpuMode::OamScan | GpuMode::Drawing),
+            _ => true,
+        }
+    }
+
+    fn blocks(&self, address: u16) -> bool {
+        let blocked = !self.accessible(address);
+        if blocked {
+            self.blocked_accesses.set(self.blocked_accesses.get() + 1);
         }
+        blocked
+    }
+
+    pub fn blocked_accesses(&self) -> u64 {
+        self.blocked_accesses.get()
     }
 
     pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        if self.blocks(address) {
+            return Ok(0xFF);
+        }
         Ok(match address {
             0x8000..=0x9FFF => self.vram[(address - 0x8000) as usize],
             0xFE00..=0xFE9F => self.oam[(address - 0xFE00) as usize],
+            0xFF40 => self.lcdc,
+            0xFF41 => 0x80 | self.stat,
+            0xFF42 => self.scroll_y,
+            0xFF43 => self.scroll_x,
+            0xFF44 => self.ly,
+            0xFF45 => self.lyc,
+            0xFF47 => self.pallettes[0],
+            0xFF48 => self.pallettes[1],
+            0xFF49 => self.pallettes[2],
+            0xFF4A => self.window_x_y[1],
+            0xFF4B => self.window_x_y[0],
+            0xFF4F => 0xFE | self.vram_bank,
+            // HDMA source and destination are write-only. No transfer is
+            // ever active, which HDMA5 reports as 0xFF
+            0xFF51..=0xFF55 => 0xFF,
             _ => return Err(MemoryAddressError),
         })
     }
 
     pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+            if self.blocks(address) {
+                return Ok(());
+            }
             match address {
-                0x8000..=0x9FFF => self.vram[(address - 0x8000) as usize] = value,
+                0x8000..=0x9FFF => {
+                    let offset = (address - 0x8000) as usize;
+                    self.vram[offset] = value;
+                    if let Some(dirty) = self.dirty_tiles.get_mut(offset / 16) {
+                        *dirty = true;
+                    }
+                },
                 0xFE00..=0xFE9F => self.oam[(address - 0xFE00) as usize] = value,
+                0xFF40 => self.write_lcdc(value),
+                // The mode and coincidence bits are read-only
+                0xFF41 => self.stat = (self.stat & 0x07) | (value & 0x78),
+                0xFF42 => self.scroll_y = value,
+                0xFF43 => self.scroll_x = value,
+                // LY is read-only
+                0xFF44 => {},
+                0xFF45 => self.lyc = value,
+                0xFF47 => self.pallettes[0] = value,
+                0xFF48 => self.pallettes[1] = value,
+                0xFF49 => self.pallettes[2] = value,
+                0xFF4A => self.window_x_y[1] = value,
+                0xFF4B => self.window_x_y[0] = value,
+                0xFF4F => self.vram_bank = value & 0x01,
+                0xFF51..=0xFF55 => self.hdma[(address - 0xFF51) as usize] = value,
                 _ => return Err(MemoryAddressError)
             }
             Ok(())
     }
     
-    pub fn assemble_tiles(&mut self) {
-        for i in 0..512 {
-            self.tiles[i] = Self::load_tile_from_bytes(&self.vram[i*16..(i+1)*16]) 
+    // Re-decodes the tiles written since the last call
+    fn update_tiles(&mut self) {
+        for (i, dirty) in self.dirty_tiles.iter_mut().enumerate() {
+            if std::mem::take(dirty) {
+                self.tiles[i] = Self::load_tile_from_bytes(&self.vram[i*16..(i+1)*16]);
+            }
         }
     }
 
@@ -113,44 +321,393 @@ impl Gpu {
         Tile { pixels }
     }
     
-    pub fn step(&mut self, cycles: i32) {
+    // OAM DMA reads VRAM on its own bus, whatever mode the PPU is in
+    pub fn read_vram_raw(&self, address: u16) -> u8 {
+        self.vram[(address - 0x8000) as usize]
+    }
+
+    // OAM DMA writes straight into OAM
+    pub fn write_oam(&mut self, index: usize, value: u8) {
+        self.oam[index] = value;
+    }
+
+    pub fn frame_buffer(&self) -> &[Pixel] {
+        &self.frame_buffer
+    }
+
+    // Offset into VRAM of a tile's data. With LCDC bit 4 clear the index is
+    // signed and relative to 0x9000
+    fn tile_address(&self, tile: u8) -> usize {
+        if self.lcdc & LCDC_TILE_DATA != 0 {
+            tile as usize * 16
+        } else {
+            (0x1000 + tile as i8 as i32 * 16) as usize
+        }
+    }
+
+    // 2-bit colour index of one pixel in a tile
+    fn tile_pixel(&self, address: usize, x: u8, y: u8) -> u8 {
+        debug_assert!(x < 8 && y < 8);
+        self.tiles[address / 16].pixels[y as usize * 8 + x as usize].index()
+    }
+
+    fn render_scanline(&mut self) {
+        self.render_background();
+        self.render_sprites();
+    }
+
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc & LCDC_OBJ_SIZE != 0 { 16 } else { 8 }
+    }
+
+    // Picks the first 10 sprites in OAM order that overlap this line. Their
+    // X position doesn't matter, off-screen sprites still count to the limit
+    fn scan_oam(&mut self) {
+        let height = self.sprite_height();
+        let line = self.ly.wrapping_add(16);
+        self.line_sprites.clear();
+        for (index, sprite) in self.oam.chunks_exact(4).enumerate() {
+            let y = sprite[0];
+            if line >= y && line < y.wrapping_add(height) {
+                self.line_sprites.push(index);
+                if self.line_sprites.len() == SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+        // On DMG the sprite with the smaller X wins, then the one earlier in OAM
+        let oam = &self.oam;
+        self.line_sprites.sort_by_key(|&index| (oam[index * 4 + 1], index));
+    }
+
+    // 2-bit colour index of a sprite at screen x on this line, 0 where it's
+    // transparent or doesn't cover x
+    fn sprite_pixel(&self, index: usize, x: usize) -> u8 {
+        let left = self.oam[index * 4 + 1] as usize;
+        if x + 8 < left || x + 8 >= left + 8 {
+            return 0;
+        }
+        self.sprite_column(index, (x + 8 - left) as u8)
+    }
+
+    // 2-bit colour index of one of the 8 columns of a sprite on this line
+    fn sprite_column(&self, index: usize, mut column: u8) -> u8 {
+        let sprite = &self.oam[index * 4..index * 4 + 4];
+        let (y, mut tile, attributes) = (sprite[0], sprite[2], sprite[3]);
+        let height = self.sprite_height();
+        // LCDC can switch sizes after the OAM scan, keep the row in range
+        let mut row = self.ly.wrapping_add(16).wrapping_sub(y) & (height - 1);
+        if attributes & OBJ_Y_FLIP != 0 {
+            row = height - 1 - row;
+        }
+        // 8x16 sprites are two tiles, the top one even
+        if height == 16 {
+            tile = if row < 8 { tile & 0xFE } else { tile | 0x01 };
+            row %= 8;
+        }
+        if attributes & OBJ_X_FLIP != 0 {
+            column = 7 - column;
+        }
+        // Sprites always use the unsigned 0x8000 tile data
+        self.tile_pixel(tile as usize * 16, column, row)
+    }
+
+    fn bg_pixel(&self, index: u8) -> Pixel {
+        Pixel {
+            color: Color::from_palette(self.pallettes[0], index),
+            layer: Layer::Bg,
+        }
+    }
+
+    fn obj_pixel(&self, attributes: u8, index: u8) -> Pixel {
+        if attributes & OBJ_PALETTE != 0 {
+            Pixel { color: Color::from_palette(self.pallettes[2], index), layer: Layer::Obj1 }
+        } else {
+            Pixel { color: Color::from_palette(self.pallettes[1], index), layer: Layer::Obj0 }
+        }
+    }
+
+    fn render_sprites(&mut self) {
+        if self.lcdc & LCDC_OBJ_ENABLE == 0 {
+            return;
+        }
+        let start = self.ly as usize * SCREEN_WIDTH;
+        for x in 0..SCREEN_WIDTH {
+            // The highest priority opaque sprite takes the pixel, even when
+            // it ends up hidden behind the background
+            let Some((index, color)) = self.line_sprites
+                .iter()
+                .map(|&index| (index, self.sprite_pixel(index, x)))
+                .find(|&(_, color)| color != 0)
+            else {
+                continue;
+            };
+            let attributes = self.oam[index * 4 + 3];
+            if attributes & OBJ_BEHIND_BG != 0 && self.bg_indices[x] != 0 {
+                continue;
+            }
+            self.frame_buffer[start + x] = self.obj_pixel(attributes, color);
+        }
+    }
+
+    // 2-bit colour index at (x, y) of the 256x256 picture a tile map forms
+    fn map_pixel(&self, map: usize, x: u8, y: u8) -> u8 {
+        let tile = self.vram[map + (y / 8) as usize * 32 + (x / 8) as usize];
+        self.tile_pixel(self.tile_address(tile), x % 8, y % 8)
+    }
+
+    // Draws the background and the window on top of it
+    fn render_background(&mut self) {
+        let start = self.ly as usize * SCREEN_WIDTH;
+        // On DMG clearing LCDC bit 0 blanks both the background and the
+        // window to white
+        if self.lcdc & LCDC_BG_ENABLE == 0 {
+            self.frame_buffer[start..start + SCREEN_WIDTH].fill(Pixel::BLANK);
+            self.bg_indices.fill(0);
+            return;
+        }
+        let bg_map = if self.lcdc & LCDC_BG_MAP != 0 { 0x1C00 } else { 0x1800 };
+        let window_map = if self.lcdc & LCDC_WINDOW_MAP != 0 { 0x1C00 } else { 0x1800 };
+        // WX is offset by 7, so WX=7 puts the window at the left edge
+        let window_x = self.window_x_y[0] as usize;
+        let window_visible = self.lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_triggered
+            && window_x < SCREEN_WIDTH + 7;
+        // The 256x256 background wraps around in both directions
+        let y = self.ly.wrapping_add(self.scroll_y);
+        for x in 0..SCREEN_WIDTH {
+            let index = if window_visible && x + 7 >= window_x {
+                self.map_pixel(window_map, (x + 7 - window_x) as u8, self.window_line)
+            } else {
+                self.map_pixel(bg_map, (x as u8).wrapping_add(self.scroll_x), y)
+            };
+            self.bg_indices[x] = index;
+            self.frame_buffer[start + x] = self.bg_pixel(index);
+        }
+        if window_visible {
+            self.window_line += 1;
+        }
+    }
+
+    pub fn take_frame_ready(&mut self) -> bool {
+        std::mem::take(&mut self.frame_ready)
+    }
+
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled = self.lcdc & LCDC_ENABLE != 0;
+        self.lcdc = value;
+        let enabled = value & LCDC_ENABLE != 0;
+        if was_enabled && !enabled {
+            // The PPU parks at the start of line 0 in mode 0 with a white screen
+            self.ly = 0;
+            self.cycles = 0;
+            self.set_mode(GpuMode::HBlank);
+            self.frame_buffer.fill(Pixel::BLANK);
+        } else if !was_enabled && enabled {
+            self.cycles = 0;
+            self.window_triggered = false;
+            self.window_line = 0;
+            self.skip_frame = true;
+            self.set_mode(GpuMode::OamScan);
+        }
+    }
+
+    fn set_mode(&mut self, mode: GpuMode) {
+        self.stat = (self.stat & !0x03) | mode.bits();
+        self.gpu_mode = mode;
+    }
+
+    // Refreshes the coincidence flag and requests the STAT interrupt on a
+    // rising edge of the STAT line
+    fn update_stat(&mut self, interrupts: &mut Interrupts) {
+        if self.ly == self.lyc {
+            self.stat |= STAT_COINCIDENCE;
+        } else {
+            self.stat &= !STAT_COINCIDENCE;
+        }
+        let source = match self.gpu_mode {
+            GpuMode::HBlank => STAT_HBLANK_INTERRUPT,
+            GpuMode::VBlank => STAT_VBLANK_INTERRUPT,
+            GpuMode::OamScan => STAT_OAM_INTERRUPT,
+            GpuMode::Drawing => 0,
+        };
+        let line = self.stat & source != 0
+            || self.stat & (STAT_COINCIDENCE | STAT_COINCIDENCE_INTERRUPT) == STAT_COINCIDENCE | STAT_COINCIDENCE_INTERRUPT;
+        if line && !self.stat_line {
+            interrupts.request(Interrupt::LcdStat);
+        }
+        self.stat_line = line;
+    }
+
+    pub fn step(&mut self, cycles: i32, interrupts: &mut Interrupts) {
+        // With the LCD off nothing is drawn and no interrupts fire, but frames
+        // still go by so the frontend keeps updating
+        if self.lcdc & LCDC_ENABLE == 0 {
+            self.cycles += cycles;
+            if self.cycles >= FRAME_CYCLES {
+                self.cycles -= FRAME_CYCLES;
+                self.frame_ready = true;
+            }
+            return;
+        }
+        match self.renderer {
+            Renderer::Scanline => self.advance(cycles, interrupts),
+            // The FIFO has to see every dot
+            Renderer::Fifo => {
+                for _ in 0..cycles {
+                    self.advance(1, interrupts);
+                }
+            },
+        }
+    }
+
+    fn advance(&mut self, cycles: i32, interrupts: &mut Interrupts) {
         self.cycles += cycles;
         match self.gpu_mode {
             GpuMode::OamScan => {
                 if self.cycles >= 80 {
-                    self.gpu_mode = GpuMode::Drawing;
+                    self.scan_oam();
+                    // VRAM is locked from here until HBlank, so the cache
+                    // stays valid for the whole line
+                    self.update_tiles();
+                    if self.ly == self.window_x_y[1] {
+                        self.window_triggered = true;
+                    }
+                    if self.renderer == Renderer::Fifo {
+                        self.fifo.start_line(self.scroll_x);
+                    }
+                    self.set_mode(GpuMode::Drawing);
                     self.cycles = 0
                 }
             },
-            GpuMode::Drawing => {
-                if self.cycles >= 172 {
-                    self.gpu_mode = GpuMode::HBlank;
-                    self.cycles = 0;
-                }
-            }
+            GpuMode::Drawing => match self.renderer {
+                Renderer::Scanline => {
+                    if self.cycles >= 172 {
+                        if !self.skip_frame {
+                            self.render_scanline();
+                        }
+                        self.hblank_cycles = 204;
+                        self.set_mode(GpuMode::HBlank);
+                        self.cycles = 0;
+                    }
+                },
+                Renderer::Fifo => {
+                    if self.fifo_dot() {
+                        if self.fifo.window_active() {
+                            self.window_line += 1;
+                        }
+                        self.hblank_cycles = 376 - self.cycles;
+                        self.set_mode(GpuMode::HBlank);
+                        self.cycles = 0;
+                    }
+                },
+            },
             GpuMode::HBlank => {
-                if self.cycles >= 204 {
-                    self.line += 1;
+                if self.cycles >= self.hblank_cycles {
+                    self.ly += 1;
                     self.cycles = 0;
 
-                    if self.line == 144 {
-                        self.gpu_mode = GpuMode::VBlank;
+                    if self.ly == 144 {
+                        self.set_mode(GpuMode::VBlank);
+                        interrupts.request(Interrupt::VBlank);
+                        self.frame_ready = true;
+                        self.skip_frame = false;
                     } else {
-                        self.gpu_mode = GpuMode::OamScan;
+                        self.set_mode(GpuMode::OamScan);
                     }
                 }
             },
             GpuMode::VBlank => {
                 if self.cycles >= 456 {
-                    self.line += 1;
+                    self.ly += 1;
                     self.cycles = 0;
 
-                    if self.line > 153 {
-                        self.line = 0;
-                        self.gpu_mode = GpuMode::OamScan;
+                    if self.ly > 153 {
+                        self.ly = 0;
+                        self.window_triggered = false;
+                        self.window_line = 0;
+                        self.set_mode(GpuMode::OamScan);
                     }
                 }
             },
         }
+        self.update_stat(interrupts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs until the next frame has been drawn
+    fn run_frame(gpu: &mut Gpu, interrupts: &mut Interrupts) {
+        gpu.take_frame_ready();
+        while !gpu.take_frame_ready() {
+            gpu.step(4, interrupts);
+        }
+    }
+
+    #[test]
+    fn tall_sprite_draws_bottom_tile() {
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let mut gpu = Gpu::new();
+            let mut interrupts = Interrupts::new();
+            gpu.renderer = renderer;
+            run_frame(&mut gpu, &mut interrupts);
+            // Sprites on, 8x16. Tile 3 is solid colour 3, tile 2 is blank
+            gpu.write(0xFF40, 0x97).unwrap();
+            gpu.write(0xFF48, 0xE4).unwrap();
+            for offset in 0..16 {
+                gpu.write(0x8030 + offset, 0xFF).unwrap();
+            }
+            // Top-left sprite using tiles 2 and 3
+            for (offset, value) in [16, 8, 2, 0].into_iter().enumerate() {
+                gpu.write(0xFE00 + offset as u16, value).unwrap();
+            }
+            run_frame(&mut gpu, &mut interrupts);
+            let pixel = |y: usize| gpu.frame_buffer()[y * SCREEN_WIDTH].color.index();
+            assert_eq!(pixel(7), 0, "{:?}", renderer);
+            assert_eq!(pixel(8), 3, "{:?}", renderer);
+        }
+    }
+
+    #[test]
+    fn disabled_background_is_white() {
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let mut gpu = Gpu::new();
+            let mut interrupts = Interrupts::new();
+            gpu.renderer = renderer;
+            run_frame(&mut gpu, &mut interrupts);
+            // BG off, with BGP mapping every index to black
+            gpu.write(0xFF40, 0x90).unwrap();
+            gpu.write(0xFF47, 0xFF).unwrap();
+            run_frame(&mut gpu, &mut interrupts);
+            assert_eq!(gpu.frame_buffer()[0].color.index(), 0, "{:?}", renderer);
+        }
+    }
+
+    #[test]
+    fn window_at_left_edge_ignores_fine_scroll() {
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let mut gpu = Gpu::new();
+            let mut interrupts = Interrupts::new();
+            gpu.renderer = renderer;
+            run_frame(&mut gpu, &mut interrupts);
+            // Window on from line 0 at the left edge, using the 0x9C00 map
+            gpu.write(0xFF40, 0xF1).unwrap();
+            gpu.write(0xFF43, 3).unwrap();
+            gpu.write(0xFF4A, 0).unwrap();
+            gpu.write(0xFF4B, 7).unwrap();
+            // Tile 1 has only its first column set
+            for row in 0..8 {
+                gpu.write(0x8010 + row * 2, 0x80).unwrap();
+                gpu.write(0x8011 + row * 2, 0x80).unwrap();
+            }
+            gpu.write(0x9C00, 1).unwrap();
+            run_frame(&mut gpu, &mut interrupts);
+            let pixel = |x: usize| gpu.frame_buffer()[x].color.index();
+            assert_eq!(pixel(0), 3, "{:?}", renderer);
+            assert_eq!(pixel(1), 0, "{:?}", renderer);
+        }
     }
 }
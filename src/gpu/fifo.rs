@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use super::{
+    Gpu, Pixel, SCREEN_WIDTH, LCDC_BG_ENABLE, LCDC_BG_MAP, LCDC_OBJ_ENABLE, LCDC_WINDOW_ENABLE,
+    LCDC_WINDOW_MAP, OBJ_BEHIND_BG,
+};
+
+// Each fetcher step besides pushing takes two dots
+const FETCH_STEP_DOTS: u8 = 2;
+// The fetcher fetches the first tile of a line twice and throws the first
+// result away
+const LINE_START_DOTS: u8 = 6;
+// Fetching a sprite's row stalls the pipeline
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum FetchStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Copy, Clone, Default)]
+struct ObjPixel {
+    color: u8,
+    attributes: u8,
+}
+
+// State of the mode 3 pixel pipeline. The background fetcher fills the
+// background FIFO 8 pixels at a time, sprites get mixed into the object FIFO
+// as the LCD reaches them, and one pixel is shifted out of both per dot
+#[derive(Clone)]
+pub struct PixelFifo {
+    bg: VecDeque<u8>,
+    obj: VecDeque<ObjPixel>,
+    step: FetchStep,
+    step_dots: u8,
+    // Tile column the fetcher is on, from the start of the line or window
+    fetch_x: u8,
+    tile: u8,
+    low: u8,
+    high: u8,
+    // Pixels sent to the LCD on this line
+    x: usize,
+    // Pixels left to drop, for SCX fine scroll or a window left of WX=7
+    discard: u8,
+    // Dots the whole pipeline waits for
+    stall: u8,
+    window_active: bool,
+    // Next entry of the line's sprites, which are sorted by X
+    next_sprite: usize,
+}
+
+impl PixelFifo {
+    pub fn new() -> Self {
+        Self {
+            bg: VecDeque::with_capacity(16),
+            obj: VecDeque::with_capacity(8),
+            step: FetchStep::Tile,
+            step_dots: 0,
+            fetch_x: 0,
+            tile: 0,
+            low: 0,
+            high: 0,
+            x: 0,
+            discard: 0,
+            stall: 0,
+            window_active: false,
+            next_sprite: 0,
+        }
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.bg.clear();
+        self.step = FetchStep::Tile;
+        self.step_dots = 0;
+        self.fetch_x = 0;
+    }
+
+    pub fn start_line(&mut self, scroll_x: u8) {
+        self.restart_fetcher();
+        self.obj.clear();
+        self.x = 0;
+        self.discard = scroll_x % 8;
+        self.stall = LINE_START_DOTS;
+        self.window_active = false;
+        self.next_sprite = 0;
+    }
+
+    pub fn window_active(&self) -> bool {
+        self.window_active
+    }
+}
+
+impl Gpu {
+    // Runs mode 3 for one dot. Returns true once all 160 pixels are out
+    pub(super) fn fifo_dot(&mut self) -> bool {
+        self.fifo_check_window();
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+        self.fifo_fetch();
+        if self.fifo.discard == 0 && !self.fifo.bg.is_empty() && self.fifo_check_sprite() {
+            return false;
+        }
+        let Some(bg) = self.fifo.bg.pop_front() else {
+            return false;
+        };
+        let obj = self.fifo.obj.pop_front().unwrap_or_default();
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+        if !self.skip_frame {
+            let color = self.mix_pixel(bg, obj);
+            self.frame_buffer[self.ly as usize * SCREEN_WIDTH + self.fifo.x] = color;
+        }
+        self.fifo.x += 1;
+        self.fifo.x == SCREEN_WIDTH
+    }
+
+    // Palettes and enable bits are looked up as each pixel leaves the FIFO,
+    // so mid-line writes show up from the next pixel on
+    fn mix_pixel(&self, bg: u8, obj: ObjPixel) -> Pixel {
+        let bg_enabled = self.lcdc & LCDC_BG_ENABLE != 0;
+        let bg = if bg_enabled { bg } else { 0 };
+        let obj_visible = self.lcdc & LCDC_OBJ_ENABLE != 0
+            && obj.color != 0
+            && (obj.attributes & OBJ_BEHIND_BG == 0 || bg == 0);
+        if obj_visible {
+            self.obj_pixel(obj.attributes, obj.color)
+        } else if bg_enabled {
+            self.bg_pixel(bg)
+        } else {
+            // Like the scanline renderer, a disabled background is white
+            // whatever BGP says
+            Pixel::BLANK
+        }
+    }
+
+    // Reaching WX throws away the background pixels and restarts the fetcher
+    // on the window map
+    fn fifo_check_window(&mut self) {
+        let window_x = self.window_x_y[0] as usize;
+        if self.fifo.window_active
+            || self.lcdc & LCDC_WINDOW_ENABLE == 0
+            || !self.window_triggered
+            || self.fifo.x + 7 < window_x
+        {
+            return;
+        }
+        self.fifo.restart_fetcher();
+        self.fifo.window_active = true;
+        // A window starting at the left edge replaces the background's fine
+        // scroll with its own offset, which is nothing at WX=7
+        if self.fifo.x == 0 {
+            self.fifo.discard = 7usize.saturating_sub(window_x) as u8;
+        }
+    }
+
+    // Mixes in the row of the next sprite once the LCD reaches its X.
+    // Returns true if that stalled the pipeline
+    fn fifo_check_sprite(&mut self) -> bool {
+        if self.lcdc & LCDC_OBJ_ENABLE == 0 {
+            return false;
+        }
+        let Some(&index) = self.line_sprites.get(self.fifo.next_sprite) else {
+            return false;
+        };
+        let left = self.oam[index * 4 + 1] as usize;
+        if left > self.fifo.x + 8 {
+            return false;
+        }
+        self.fifo.next_sprite += 1;
+        self.fifo.stall = SPRITE_FETCH_DOTS;
+        let attributes = self.oam[index * 4 + 3];
+        // Sprites hanging off the left edge lose their hidden columns
+        let first_column = (self.fifo.x + 8 - left) as u8;
+        while self.fifo.obj.len() < 8 {
+            self.fifo.obj.push_back(ObjPixel::default());
+        }
+        for column in first_column..8 {
+            let color = self.sprite_column(index, column);
+            let slot = &mut self.fifo.obj[(column - first_column) as usize];
+            // A sprite already in the FIFO has priority, it came first in X
+            // or in OAM
+            if slot.color == 0 {
+                *slot = ObjPixel { color, attributes };
+            }
+        }
+        true
+    }
+
+    fn fifo_fetch(&mut self) {
+        if self.fifo.step == FetchStep::Push {
+            // The 8 new pixels only go in once the FIFO has run dry
+            if self.fifo.bg.is_empty() {
+                let (low, high) = (self.fifo.low, self.fifo.high);
+                self.fifo.bg.extend((0..8).rev().map(|bit| (((high >> bit) & 1) << 1) | ((low >> bit) & 1)));
+                self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+                self.fifo.step = FetchStep::Tile;
+            }
+            return;
+        }
+        self.fifo.step_dots += 1;
+        if self.fifo.step_dots < FETCH_STEP_DOTS {
+            return;
+        }
+        self.fifo.step_dots = 0;
+        // SCX, SCY and LCDC are read when the fetcher gets to them
+        let (map, x, y) = if self.fifo.window_active {
+            let map = if self.lcdc & LCDC_WINDOW_MAP != 0 { 0x1C00 } else { 0x1800 };
+            (map, self.fifo.fetch_x, self.window_line)
+        } else {
+            let map = if self.lcdc & LCDC_BG_MAP != 0 { 0x1C00 } else { 0x1800 };
+            (map, (self.scroll_x / 8).wrapping_add(self.fifo.fetch_x), self.ly.wrapping_add(self.scroll_y))
+        };
+        let row = (y % 8) as usize * 2;
+        match self.fifo.step {
+            FetchStep::Tile => {
+                self.fifo.tile = self.vram[map + (y / 8) as usize * 32 + (x % 32) as usize];
+                self.fifo.step = FetchStep::DataLow;
+            },
+            FetchStep::DataLow => {
+                self.fifo.low = self.vram[self.tile_address(self.fifo.tile) + row];
+                self.fifo.step = FetchStep::DataHigh;
+            },
+            FetchStep::DataHigh => {
+                self.fifo.high = self.vram[self.tile_address(self.fifo.tile) + row + 1];
+                self.fifo.step = FetchStep::Push;
+            },
+            FetchStep::Push => unreachable!(),
+        }
+    }
+}
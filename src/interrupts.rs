@@ -0,0 +1,94 @@
+use crate::MemoryAddressError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // Ordered from highest to lowest priority
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    pub fn bit(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0b0000_0001,
+            Interrupt::LcdStat => 0b0000_0010,
+            Interrupt::Timer => 0b0000_0100,
+            Interrupt::Serial => 0b0000_1000,
+            Interrupt::Joypad => 0b0001_0000,
+        }
+    }
+
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x0040,
+            Interrupt::LcdStat => 0x0048,
+            Interrupt::Timer => 0x0050,
+            Interrupt::Serial => 0x0058,
+            Interrupt::Joypad => 0x0060,
+        }
+    }
+}
+
+// IF (0xFF0F) and IE (0xFFFF). Components raise requests here and the CPU
+// services them between instructions
+#[derive(Clone)]
+pub struct Interrupts {
+    pub flag: u8,
+    pub enable: u8,
+}
+
+impl Interrupts {
+    pub fn new() -> Self {
+        Self {
+            flag: 0,
+            enable: 0,
+        }
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flag |= interrupt.bit();
+    }
+
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flag &= !interrupt.bit();
+    }
+
+    // Requested and enabled, regardless of IME
+    pub fn pending(&self) -> u8 {
+        self.flag & self.enable & 0x1F
+    }
+
+    pub fn highest_priority(&self) -> Option<Interrupt> {
+        let pending = self.pending();
+        Interrupt::ALL.into_iter().find(|i| pending & i.bit() != 0)
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        Ok(match address {
+            // Upper 3 bits of IF are unused and always read back as 1
+            0xFF0F => self.flag | 0xE0,
+            0xFFFF => self.enable,
+            _ => return Err(MemoryAddressError),
+        })
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            0xFF0F => self.flag = value & 0x1F,
+            0xFFFF => self.enable = value,
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::interrupts::{Interrupt, Interrupts};
+use crate::MemoryAddressError;
+
+const SELECT_DPAD: u8 = 0b0001_0000;
+const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    // The d-pad sits on the low nibble, the buttons on the high nibble. Each
+    // nibble lines up with input lines P10-P13
+    fn mask(self) -> u8 {
+        match self {
+            Button::Right => 0b0000_0001,
+            Button::Left => 0b0000_0010,
+            Button::Up => 0b0000_0100,
+            Button::Down => 0b0000_1000,
+            Button::A => 0b0001_0000,
+            Button::B => 0b0010_0000,
+            Button::Select => 0b0100_0000,
+            Button::Start => 0b1000_0000,
+        }
+    }
+}
+
+// JOYP (0xFF00). Bits 4-5 select the button groups (P14/P15, active low),
+// bits 0-3 read back the selected buttons, active low
+#[derive(Clone)]
+pub struct Joypad {
+    select: u8,
+    pressed: u8,
+    // Input lines as of the last step, to catch high-to-low transitions
+    last_lines: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            select: 0x30,
+            pressed: 0,
+            last_lines: 0x0F,
+        }
+    }
+
+    pub fn set_pressed(&mut self, buttons: &[Button]) {
+        self.pressed = buttons.iter().fold(0, |mask, button| mask | button.mask());
+    }
+
+    // P10-P13, active low
+    pub fn lines(&self) -> u8 {
+        let mut lines = 0x0F;
+        if self.select & SELECT_DPAD == 0 {
+            lines &= !(self.pressed & 0x0F);
+        }
+        if self.select & SELECT_BUTTONS == 0 {
+            lines &= !(self.pressed >> 4);
+        }
+        lines
+    }
+
+    // Lines can fall from a button press or from the game changing the select
+    // bits, either one requests the joypad interrupt
+    pub fn step(&mut self, interrupts: &mut Interrupts) {
+        let lines = self.lines();
+        if self.last_lines & !lines != 0 {
+            interrupts.request(Interrupt::Joypad);
+        }
+        self.last_lines = lines;
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        match address {
+            0xFF00 => Ok(0xC0 | self.select | self.lines()),
+            _ => Err(MemoryAddressError),
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            0xFF00 => self.select = value & 0x30,
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
@@ -1,39 +1,96 @@
+mod apu;
+mod cartridge;
+mod config;
 mod cpu;
+mod dma;
 mod gpu;
+mod interrupts;
+mod joypad;
+mod mapper;
 mod memory;
+mod palette;
 mod screen;
+mod serial;
+mod timer;
 
-use std::fs::File;
-use std::io::Read;
+use std::cell::Cell;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use cpu::Cpu;
+use cartridge::{Cartridge, CgbSupport};
+use config::{Config, Hotkey};
+use cpu::{Cpu, ImeDelay};
 use cpu::{Register8, Register16, Flag};
 use cpu::Register8::*;
 use cpu::Register16::*;
-use gpu::Gpu;
+use apu::Apu;
+use dma::Dma;
+use gpu::{Gpu, Renderer};
+use interrupts::Interrupts;
+use joypad::Joypad;
 use memory::Memory;
+use screen::Screen;
+use serial::Serial;
+use timer::Timer;
 
 use thiserror::Error;
 use anyhow::Result;
 
-fn main() {
-    let mut gb = Gameboy {
-        cpu: Cpu::default(),
-        memory: Memory::new(),
-        gpu: Gpu::new(),
+const DEFAULT_CONFIG: &str = "rustboy.toml";
+// Frames emulated per displayed frame while fast-forwarding
+const FAST_FORWARD_FRAMES: usize = 4;
+
+fn main() -> Result<()> {
+    let mut rom_path = None;
+    let mut rtc_sync = false;
+    let mut strict = false;
+    let mut fifo = false;
+    let mut config_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rtc-sync" => rtc_sync = true,
+            "--strict" => strict = true,
+            "--fifo" => fifo = true,
+            "--config" => config_path = args.next(),
+            _ => rom_path = Some(arg),
+        }
+    }
+    let Some(rom_path) = rom_path else {
+        eprintln!("Usage: rustboy [--rtc-sync] [--strict] [--fifo] [--config <file>] <rom>");
+        std::process::exit(1);
+    };
+    // Without --config, pick up rustboy.toml from the working directory if
+    // there is one
+    let config = match config_path {
+        Some(path) => Config::load(Path::new(&path))?,
+        None if Path::new(DEFAULT_CONFIG).exists() => Config::load(Path::new(DEFAULT_CONFIG))?,
+        None => Config::default(),
     };
-    gb.cpu.pc = 0x0100;
-    gb.cpu.sp = 0xFFFE;
-    // ERROR: ROM isn't being read properly
-    let mut f = File::open("/home/mukund/projects/rust/rustboy/tetris.gb").unwrap();
-    let mut buff: Vec<u8> = vec![];
-    f.read_to_end(&mut buff).unwrap();
-    for (index, byte) in buff.bytes().enumerate() {
-        let _ = gb.write(index as u16, byte.unwrap());
+    let mut cartridge = Cartridge::load(Path::new(&rom_path))?;
+    if rtc_sync {
+        cartridge.sync_rtc_to_host();
+    }
+    let header = &cartridge.header;
+    println!(
+        "Loaded \"{}\" v{} ({:?}, licensee {:?}, CGB {:?}, SGB {})",
+        header.title, header.version, header.cartridge_type.mapper, header.licensee, header.cgb, header.sgb,
+    );
+    let mut gb = Gameboy::new(cartridge);
+    gb.strict = strict;
+    if fifo {
+        gb.gpu.renderer = Renderer::Fifo;
+    }
+    let mut screen = Screen::new(config);
+    let result = gb.run(&mut screen);
+    if gb.strict && gb.gpu.blocked_accesses() > 0 {
+        eprintln!("[strict] {} VRAM/OAM accesses blocked by the PPU", gb.gpu.blocked_accesses());
+    }
+    if let Err(e) = gb.cartridge.save() {
+        eprintln!("Failed to write save file: {}", e);
     }
-    gb.gpu.assemble_tiles();
-    // screen::render(&gb.gpu.tiles[..10]);
-    let _ = gb.run();
+    result
 }
 
 #[derive(Debug, Error)]
@@ -41,11 +98,12 @@ fn main() {
 pub struct MemoryAddressError;
 
 
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Debug)]
 enum Opcode {
     NOP,
     HALT,
+    STOP,
     LD_R_R { target: Register8, source: Register8 },
     LD_R_HL { target: Register8 },
     LD_HL_R { source: Register8 },
@@ -145,67 +203,397 @@ enum Opcode {
     EI,
 }
 
+// Screenshots land in the working directory, named after the time they
+// were taken
+fn screenshot_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    PathBuf::from(format!("screenshot-{}.ppm", millis))
+}
+
+#[derive(Clone)]
 struct Gameboy {
     cpu: Cpu,
     memory: Memory,
     gpu: Gpu,
+    interrupts: Interrupts,
+    joypad: Joypad,
+    serial: Serial,
+    timer: Timer,
+    dma: Dma,
+    apu: Apu,
+    cgb_mode: bool,
+    cartridge: Cartridge,
+    // Log accesses to unmapped or disabled memory instead of silently
+    // returning open bus values
+    strict: bool,
+    // Instructions run in one go, but each bus access takes an M-cycle. CPU
+    // cycles of the current step up to the latest access, and how many of
+    // them the PPU has already been run for
+    bus_cycles: Cell<i32>,
+    gpu_cycles: i32,
 }
 
 impl Gameboy {
+    fn new(cartridge: Cartridge) -> Self {
+        let mut gb = Self {
+            cpu: Cpu::default(),
+            memory: Memory::new(),
+            gpu: Gpu::new(),
+            interrupts: Interrupts::new(),
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            timer: Timer::new(),
+            dma: Dma::new(),
+            apu: Apu::new(),
+            cgb_mode: cartridge.header.cgb != CgbSupport::Dmg,
+            cartridge,
+            strict: false,
+            bus_cycles: Cell::new(0),
+            gpu_cycles: 0,
+        };
+        gb.reset();
+        gb
+    }
+
+    // Power cycles the console. Only the cartridge's battery-backed RAM and
+    // RTC survive
+    fn reset(&mut self) {
+        self.cartridge.reset();
+        self.cpu = Cpu::default();
+        self.memory = Memory::new();
+        let renderer = self.gpu.renderer;
+        self.gpu = Gpu::new();
+        self.gpu.renderer = renderer;
+        self.interrupts = Interrupts::new();
+        self.joypad = Joypad::new();
+        self.serial = Serial::new();
+        self.timer = Timer::new();
+        self.dma = Dma::new();
+        self.apu = Apu::new();
+        self.cpu.pc = 0x0100;
+        self.cpu.sp = 0xFFFE;
+    }
 
     fn push(&mut self, value: u8) -> Result<()> {
         self.cpu.sp -= 1;
-        self.memory.write(self.cpu.sp, value)?;
+        self.write(self.cpu.sp, value)?;
         Ok(())
     }
 
     fn pop(&mut self) -> Result<u8> {
-        let value = self.memory.read(self.cpu.sp)?;
+        let value = self.read(self.cpu.sp)?;
         self.cpu.sp += 1;
         Ok(value)
     }
 
+    // CPU side of the bus. During OAM DMA only HRAM is reachable, everything
+    // else reads whatever byte the DMA is moving and ignores writes
     fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        self.bus_cycles.set(self.bus_cycles.get() + 4);
+        if self.dma.active() && !(0xFF80..=0xFFFE).contains(&address) {
+            return Ok(self.dma.conflict_value());
+        }
+        self.read_bus(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        self.bus_cycles.set(self.bus_cycles.get() + 4);
+        if self.dma.active() && !(0xFF80..=0xFFFE).contains(&address) {
+            return Ok(());
+        }
+        self.write_bus(address, value)
+    }
+
+    fn read_bus(&self, address: u16) -> Result<u8, MemoryAddressError> {
         match address {
-            0x0000..=0x7FFF => self.memory.read(address),
-            0x8000..=0x9FFF => self.gpu.read(address),
-            0xA000..=0xBFFF => self.memory.read(address),
+            0x0000..=0x7FFF => self.cartridge.read(address),
+            0x8000..=0x9FFF => {
+                self.report_blocked("read", address);
+                self.gpu.read(address)
+            },
+            0xA000..=0xBFFF => {
+                if !self.cartridge.ram_accessible() {
+                    self.report_open_bus("read", address);
+                }
+                self.cartridge.read(address)
+            },
             0xC000..=0xCFFF => self.memory.read(address),
             0xD000..=0xDFFF => self.memory.read(address),
-            0xE000..=0xFDFF => self.memory.read(address),
-            0xFE00..=0xFE9F => self.gpu.read(address),
+            // Echo RAM mirrors 0xC000-0xDDFF
+            0xE000..=0xFDFF => self.memory.read(address - 0x2000),
+            0xFE00..=0xFE9F => {
+                self.report_blocked("read", address);
+                self.gpu.read(address)
+            },
+            0xFEA0..=0xFEFF => {
+                self.report_open_bus("read", address);
+                Ok(self.unusable_value(address))
+            },
+            0xFF00..=0xFF7F => self.read_io(address).or_else(|_| {
+                self.report_open_bus("read", address);
+                Ok(0xFF)
+            }),
             0xFF80..=0xFFFE => self.memory.read(address),
-            _ => Err(MemoryAddressError)
+            0xFFFF => self.interrupts.read(address),
         }
     }
 
-    fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+    fn write_bus(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
         match address {
-            0x0000..=0x7FFF => self.memory.write(address, value),
-            0x8000..=0x9FFF => self.gpu.write(address, value),
-            0xA000..=0xBFFF => self.memory.write(address, value),
+            0x0000..=0x7FFF => self.cartridge.write(address, value),
+            0x8000..=0x9FFF => {
+                self.report_blocked("write", address);
+                self.gpu.write(address, value)
+            },
+            0xA000..=0xBFFF => {
+                if !self.cartridge.ram_accessible() {
+                    self.report_open_bus("write", address);
+                }
+                self.cartridge.write(address, value)
+            },
             0xC000..=0xCFFF => self.memory.write(address, value),
             0xD000..=0xDFFF => self.memory.write(address, value),
-            0xE000..=0xFDFF => self.memory.write(address, value),
-            0xFE00..=0xFE9F => self.gpu.write(address, value),
+            0xE000..=0xFDFF => self.memory.write(address - 0x2000, value),
+            0xFE00..=0xFE9F => {
+                self.report_blocked("write", address);
+                self.gpu.write(address, value)
+            },
+            0xFEA0..=0xFEFF => {
+                self.report_open_bus("write", address);
+                Ok(())
+            },
+            0xFF00..=0xFF7F => self.write_io(address, value).or_else(|_| {
+                self.report_open_bus("write", address);
+                Ok(())
+            }),
             0xFF80..=0xFFFE => self.memory.write(address, value),
-            _ => Err(MemoryAddressError)
+            0xFFFF => self.interrupts.write(address, value),
+        }
+    }
+
+    // What reads from the prohibited 0xFEA0-0xFEFF region return. DMG reads
+    // 0x00, while CGB repeats the upper nibble of the address' low byte
+    fn unusable_value(&self, address: u16) -> u8 {
+        if self.cgb_mode {
+            let nibble = (address as u8) & 0xF0;
+            nibble | (nibble >> 4)
+        } else {
+            0x00
+        }
+    }
+
+    fn report_open_bus(&self, access: &str, address: u16) {
+        if self.strict {
+            eprintln!("[strict] {} of unmapped address {:#06X} at PC {:#06X}", access, address, self.cpu.pc);
+        }
+    }
+
+    fn report_blocked(&self, access: &str, address: u16) {
+        if self.strict && !self.gpu.accessible(address) {
+            eprintln!("[strict] {} of {:#06X} blocked by the PPU at PC {:#06X}", access, address, self.cpu.pc);
+        }
+    }
+
+    // Routes 0xFF00-0xFF7F to the component that owns each hardware register
+    fn read_io(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        match address {
+            0xFF00 => self.joypad.read(address),
+            0xFF01..=0xFF02 => self.serial.read(address),
+            0xFF04..=0xFF07 => self.timer.read(address),
+            0xFF0F => self.interrupts.read(address),
+            0xFF10..=0xFF3F => self.apu.read(address),
+            0xFF40..=0xFF45 => self.gpu.read(address),
+            0xFF46 => self.dma.read(address),
+            0xFF47..=0xFF4B => self.gpu.read(address),
+            0xFF4D if self.cgb_mode => Ok(self.cpu.read_key1()),
+            0xFF4F if self.cgb_mode => self.gpu.read(address),
+            0xFF51..=0xFF55 if self.cgb_mode => self.gpu.read(address),
+            0xFF70 if self.cgb_mode => self.memory.read(address),
+            _ => Err(MemoryAddressError),
+        }
+    }
+
+    fn write_io(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            0xFF00 => self.joypad.write(address, value),
+            0xFF01..=0xFF02 => self.serial.write(address, value),
+            0xFF04..=0xFF07 => self.timer.write(address, value),
+            0xFF0F => self.interrupts.write(address, value),
+            0xFF10..=0xFF3F => self.apu.write(address, value),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => {
+                // The PPU sees the write from the M-cycle it happens in, which
+                // matters for SCX, palette and LCDC changes in the middle of a
+                // line
+                self.step_gpu(self.bus_cycles.get() - 4);
+                self.gpu.write(address, value)
+            },
+            0xFF46 => self.dma.write(address, value),
+            0xFF4D if self.cgb_mode => {
+                self.cpu.write_key1(value);
+                Ok(())
+            },
+            0xFF4F if self.cgb_mode => self.gpu.write(address, value),
+            0xFF51..=0xFF55 if self.cgb_mode => self.gpu.write(address, value),
+            0xFF70 if self.cgb_mode => self.memory.write(address, value),
+            _ => Err(MemoryAddressError),
+        }
+    }
+
+    // Runs the PPU up to the given CPU cycle of the current step
+    fn step_gpu(&mut self, until: i32) {
+        // In double speed mode the CPU clock runs twice as fast as the PPU's
+        let (until_dots, done_dots) = if self.cpu.double_speed {
+            (until / 2, self.gpu_cycles / 2)
+        } else {
+            (until, self.gpu_cycles)
+        };
+        if until_dots > done_dots {
+            self.gpu.step(until_dots - done_dots, &mut self.interrupts);
+            self.gpu_cycles = until;
         }
     }
 
-    fn run(&mut self) -> Result<()> {
+    // Copies one byte per M-cycle of a running OAM DMA
+    fn step_dma(&mut self, cycles: i32) -> Result<()> {
+        for _ in 0..cycles / 4 {
+            let Some((address, index)) = self.dma.next_transfer() else {
+                break;
+            };
+            let value = match address {
+                0x8000..=0x9FFF => self.gpu.read_vram_raw(address),
+                _ => self.read_bus(address)?,
+            };
+            self.dma.set_conflict_value(value);
+            self.gpu.write_oam(index, value);
+        }
+        Ok(())
+    }
+
+    // Runs frames until the window is closed. The window's target FPS paces
+    // emulation to roughly real time
+    fn run(&mut self, screen: &mut Screen) -> Result<()> {
+        let mut paused = false;
+        // A single save state slot, kept in memory for the session
+        let mut saved_state: Option<Box<Gameboy>> = None;
+        while screen.is_open() {
+            for hotkey in screen.triggered_hotkeys() {
+                match hotkey {
+                    Hotkey::Pause => paused = !paused,
+                    Hotkey::Screenshot => {
+                        let path = screenshot_path();
+                        match screen.screenshot(&path) {
+                            Ok(()) => println!("Saved screenshot to {}", path.display()),
+                            Err(e) => eprintln!("Failed to write screenshot: {}", e),
+                        }
+                    },
+                    Hotkey::SaveState => saved_state = Some(Box::new(self.clone())),
+                    Hotkey::LoadState => {
+                        if let Some(state) = &saved_state {
+                            *self = (**state).clone();
+                        }
+                    },
+                    Hotkey::Reset => self.reset(),
+                    Hotkey::FastForward => {},
+                }
+            }
+            if paused {
+                screen.refresh();
+                continue;
+            }
+            let frames = if screen.hotkey_held(Hotkey::FastForward) { FAST_FORWARD_FRAMES } else { 1 };
+            self.joypad.set_pressed(&screen.pressed_buttons());
+            for _ in 0..frames {
+                self.run_frame()?;
+            }
+            screen.render(self.gpu.frame_buffer());
+        }
+        Ok(())
+    }
+
+    // Runs until the PPU finishes a frame
+    fn run_frame(&mut self) -> Result<()> {
         loop {
-            println!("{:x}: {:x}", self.read(self.cpu.pc).unwrap(), self.cpu.pc);
-            if self.cpu.pc == 0x0038 { panic!() }
-            let cycles_elapsed = self.run_single_opcode()?;
-            self.gpu.step(cycles_elapsed);
-            // todo!("timer wait accounting for clock, instruction cycles, and draw buffer");
+            self.bus_cycles.set(0);
+            self.gpu_cycles = 0;
+            let cycles_elapsed = self.step()?;
+            self.serial.step(cycles_elapsed, &mut self.interrupts);
+            self.timer.step(cycles_elapsed, &mut self.interrupts);
+            self.joypad.step(&mut self.interrupts);
+            self.step_dma(cycles_elapsed)?;
+            self.step_gpu(cycles_elapsed);
+            // In double speed mode the CPU clock runs twice as fast as the
+            // cartridge's
+            let system_cycles = if self.cpu.double_speed { cycles_elapsed / 2 } else { cycles_elapsed };
+            self.cartridge.step(system_cycles);
+            if let Some(event) = self.cartridge.poll_rumble() {
+                println!("Rumble {:?}", event);
+            }
+            if self.cartridge.save_due() {
+                if let Err(e) = self.cartridge.save() {
+                    eprintln!("Failed to write save file: {}", e);
+                }
+            }
+            if self.gpu.take_frame_ready() {
+                return Ok(());
+            }
+        }
+    }
+
+    // Runs the CPU for one instruction, interrupt dispatch or idle HALT tick
+    fn step(&mut self) -> Result<i32> {
+        let mut cycles = 0;
+        if self.cpu.stopped {
+            // Low power mode lasts until one of the joypad input lines goes low
+            if self.joypad.lines() == 0x0F {
+                return Ok(4);
+            }
+            self.cpu.stopped = false;
+        }
+        if self.cpu.halted {
+            // Fetch stays suspended until an enabled interrupt is requested,
+            // whether or not IME lets it be serviced
+            if self.interrupts.pending() == 0 {
+                return Ok(4);
+            }
+            self.cpu.halted = false;
+            cycles += 4;
+        }
+        if let Some(dispatch_cycles) = self.service_interrupt()? {
+            return Ok(cycles + dispatch_cycles);
         }
+        let opcode_cycles = self.run_single_opcode()?;
+        self.cpu.tick_ime();
+        Ok(cycles + opcode_cycles)
+    }
+
+    // Dispatches the highest priority pending interrupt, if IME allows it.
+    // Pushes PC and jumps to the interrupt vector, taking 5 M-cycles
+    fn service_interrupt(&mut self) -> Result<Option<i32>> {
+        if !self.cpu.ime {
+            return Ok(None);
+        }
+        let Some(interrupt) = self.interrupts.highest_priority() else {
+            return Ok(None);
+        };
+        self.cpu.disable_ime();
+        self.interrupts.acknowledge(interrupt);
+        let (upper, lower) = cpu::split_word(self.cpu.pc);
+        self.push(upper)?;
+        self.push(lower)?;
+        self.cpu.pc = interrupt.vector();
+        Ok(Some(20))
     }
 
     fn run_single_opcode(&mut self) -> Result<i32> {
         let opcode = self.fetch_opcode();
-        dbg!(&opcode);
+        if self.cpu.halt_bug {
+            // The byte after HALT gets read twice. Backing PC up by one makes
+            // the operands and the following fetch see it again
+            self.cpu.halt_bug = false;
+            self.cpu.pc = self.cpu.pc.wrapping_sub(1);
+        }
         self.execute(opcode)
     }
 
@@ -232,7 +620,7 @@ impl Gameboy {
             0x0E => Opcode::LD_R_N { target: C },
             0x0F => Opcode::RRCA,
             
-            0x10 => panic!("I stopped"), // todo!("STOP n8")
+            0x10 => Opcode::STOP,
             0x11 => Opcode::LD_R16_N { target: DE },
             0x12 => Opcode::LD_R16_A { target: DE },
             0x13 => Opcode::INC_R16 { target: DE },
@@ -804,7 +1192,33 @@ impl Gameboy {
                 Ok(4)
             },
             Opcode::HALT => {
-                panic!("Received HALT Opcode");
+                let interrupt_pending = self.interrupts.pending() != 0;
+                if interrupt_pending && self.cpu.ime_delay == ImeDelay::Pending {
+                    // EI; HALT with an interrupt already pending. The interrupt
+                    // is serviced right away and returns to the HALT itself
+                    return Ok(4);
+                }
+                self.cpu.pc += 1;
+                if !self.cpu.ime && interrupt_pending {
+                    // DMG HALT bug: the CPU doesn't halt and PC fails to
+                    // increment after the next opcode fetch
+                    self.cpu.halt_bug = true;
+                } else {
+                    self.cpu.halted = true;
+                }
+                Ok(4)
+            },
+            Opcode::STOP => {
+                // STOP is two bytes long, the second byte is ignored
+                self.cpu.pc += 2;
+                self.timer.reset_divider();
+                if self.cgb_mode && self.cpu.speed_switch_armed {
+                    self.cpu.speed_switch_armed = false;
+                    self.cpu.double_speed = !self.cpu.double_speed;
+                } else {
+                    self.cpu.stopped = true;
+                }
+                Ok(4)
             },
             Opcode::LD_R_R {target, source} => {
                 let value = self.cpu.read8(&source);
@@ -1657,26 +2071,26 @@ impl Gameboy {
             Opcode::LDH_a8_A => {
                 let value = self.cpu.read8(&A);
                 let target = self.read(self.cpu.pc + 1)? as u16;
-                self.write(target + 0xFF, value)?;
+                self.write(0xFF00 + target, value)?;
                 self.cpu.pc += 2;
                 Ok(12)
             },
             Opcode::LDH_A_a8 => {
                 let target = self.read(self.cpu.pc + 1)? as u16;
-                let value = self.read(target + 0xFF)?;
+                let value = self.read(0xFF00 + target)?;
                 self.cpu.write8(A, value);
                 self.cpu.pc += 2;
                 Ok(12)
             },
             Opcode::LDH_c_A => {
                 let value = self.cpu.read8(&A);
-                let target = self.cpu.read8(&C) as u16 + 0xFF;
+                let target = 0xFF00 + self.cpu.read8(&C) as u16;
                 self.write(target, value)?;
                 self.cpu.pc += 1;
                 Ok(8)
             },
             Opcode::LDH_A_c => {
-                let target = self.cpu.read8(&C) as u16 + 0xFF;   
+                let target = 0xFF00 + self.cpu.read8(&C) as u16;   
                 let value = self.read(target)?;
                 self.cpu.write8(A, value);
                 self.cpu.pc += 1;
@@ -1823,7 +2237,7 @@ impl Gameboy {
                         adj += 0x60;
                         self.cpu.set_flag(Flag::C, true);
                     }
-                    adj = target + adj;
+                    adj += target;
                 }
                 self.cpu.write8(A, adj);
                 self.cpu.set_flag(Flag::Z, adj == 0);
@@ -1856,18 +2270,18 @@ impl Gameboy {
             Opcode::RETI => {
                 let lower = self.pop()?;
                 let upper = self.pop()?;
-                self.cpu.sp += 1;
                 self.cpu.pc = cpu::join_bytes(upper, lower);
-                self.cpu.ime = true;
+                // Unlike EI, RETI enables interrupts immediately
+                self.cpu.enable_ime();
                 Ok(16)
             },
             Opcode::DI => {
-                self.cpu.ime = false;
+                self.cpu.disable_ime();
                 self.cpu.pc += 1;
                 Ok(4)
             },
             Opcode::EI => {
-                self.cpu.ime = true;
+                self.cpu.schedule_ime();
                 self.cpu.pc += 1;
                 Ok(4)
             },
@@ -0,0 +1,131 @@
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rtc;
+
+pub use mbc1::Mbc1;
+pub use mbc2::Mbc2;
+pub use mbc3::Mbc3;
+pub use mbc5::Mbc5;
+pub use rtc::Rtc;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RumbleEvent {
+    Start,
+    Stop,
+}
+
+// A cartridge's memory bank controller. It owns the ROM and external RAM
+// and decodes writes to 0x0000-0x7FFF as register writes
+pub trait Mapper: MapperClone {
+    fn read_rom(&self, address: u16) -> u8;
+    fn write_register(&mut self, address: u16, value: u8);
+    // Disabled or missing RAM reads back as 0xFF
+    fn read_ram(&self, address: u16) -> u8;
+    fn write_ram(&mut self, address: u16, value: u8);
+
+    // The whole external RAM, regardless of banking or enable state
+    fn ram(&self) -> &[u8];
+    fn ram_mut(&mut self) -> &mut [u8];
+    fn ram_enabled(&self) -> bool;
+
+    // Puts the bank and enable registers back to their power-on state. RAM
+    // and the RTC are battery-backed and keep their contents
+    fn reset(&mut self);
+
+    // Advances anything on the cartridge that runs off the system clock
+    fn step(&mut self, _cycles: i32) {}
+
+    fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        None
+    }
+
+    // Returns the latest change of the rumble motor since the last poll
+    fn poll_rumble(&mut self) -> Option<RumbleEvent> {
+        None
+    }
+}
+
+// Lets a boxed mapper be cloned along with the rest of the machine
+pub trait MapperClone {
+    fn clone_box(&self) -> Box<dyn Mapper>;
+}
+
+impl<T: Mapper + Clone + 'static> MapperClone for T {
+    fn clone_box(&self) -> Box<dyn Mapper> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Mapper> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Byte offset into ROM for `address` within a switchable 16 KiB window
+fn rom_offset(rom: &[u8], bank: usize, address: u16) -> usize {
+    let banks = rom.len() / ROM_BANK_SIZE;
+    (bank % banks) * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))
+}
+
+// Byte offset into RAM for `address` within a switchable 8 KiB window.
+// Carts with less than 8 KiB mirror it across the window
+fn ram_offset(ram: &[u8], bank: usize, address: u16) -> usize {
+    (bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))) % ram.len()
+}
+
+#[derive(Clone)]
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, address: u16) -> u8 {
+        self.rom[address as usize]
+    }
+
+    fn write_register(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(&self.ram, 0, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram.is_empty() {
+            let offset = ram_offset(&self.ram, 0, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,144 @@
+use super::{ram_offset, rom_offset, Mapper};
+
+#[derive(Clone)]
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 5-bit ROM bank number, 0x2000-0x3FFF
+    bank1: u8,
+    // 2-bit upper ROM bank / RAM bank number, 0x4000-0x5FFF
+    bank2: u8,
+    // Banking mode select, 0x6000-0x7FFF. In mode 1 `bank2` also applies to
+    // 0x0000-0x3FFF and to RAM
+    advanced_mode: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            advanced_mode: false,
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_mode { self.bank2 as usize } else { 0 }
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            // On 1 MiB+ carts mode 1 maps banks 0x20/0x40/0x60 here
+            0x0000..=0x3FFF if self.advanced_mode => (self.bank2 as usize) << 5,
+            0x0000..=0x3FFF => 0,
+            _ => ((self.bank2 as usize) << 5) | self.bank1 as usize,
+        };
+        self.rom[rom_offset(&self.rom, bank, address)]
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Bank 0 can't be selected here, so 0x00/0x20/0x40/0x60 become
+                // 0x01/0x21/0x41/0x61
+                self.bank1 = (value & 0x1F).max(1);
+            },
+            0x4000..=0x5FFF => self.bank2 = value & 0x03,
+            0x6000..=0x7FFF => self.advanced_mode = value & 0x01 != 0,
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(&self.ram, self.ram_bank(), address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset = ram_offset(&self.ram, self.ram_bank(), address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn reset(&mut self) {
+        self.ram_enabled = false;
+        self.bank1 = 1;
+        self.bank2 = 0;
+        self.advanced_mode = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::ROM_BANK_SIZE;
+
+    // 1 MiB ROM whose banks each start with their own bank number
+    fn large_cart() -> Mbc1 {
+        let mut rom = vec![0; 64 * ROM_BANK_SIZE];
+        for (bank, data) in rom.chunks_exact_mut(ROM_BANK_SIZE).enumerate() {
+            data[0] = bank as u8;
+        }
+        Mbc1::new(rom, 0x8000)
+    }
+
+    #[test]
+    fn bank_0_selects_bank_1() {
+        let mut mbc = large_cart();
+        for (value, bank) in [(0x00, 0x01), (0x20, 0x01), (0x1F, 0x1F)] {
+            mbc.write_register(0x2000, value);
+            assert_eq!(mbc.read_rom(0x4000), bank);
+        }
+        // The remap only looks at the low 5 bits, 0x20 is reached through bank2
+        mbc.write_register(0x2000, 0x00);
+        mbc.write_register(0x4000, 0x01);
+        assert_eq!(mbc.read_rom(0x4000), 0x21);
+    }
+
+    #[test]
+    fn mode_1_remaps_bank_0_area() {
+        let mut mbc = large_cart();
+        mbc.write_register(0x4000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+        mbc.write_register(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x20);
+        assert_eq!(mbc.read_rom(0x4000), 0x21);
+    }
+
+    #[test]
+    fn reset_restores_power_on_banking() {
+        let mut mbc = large_cart();
+        mbc.write_register(0x0000, 0x0A);
+        mbc.write_register(0x2000, 0x05);
+        mbc.write_register(0x4000, 0x01);
+        mbc.write_register(0x6000, 0x01);
+        mbc.write_ram(0xA000, 0x42);
+        mbc.reset();
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+        assert!(!mbc.ram_enabled());
+        assert_eq!(mbc.ram()[0x2000], 0x42);
+    }
+}
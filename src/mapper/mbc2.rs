@@ -0,0 +1,74 @@
+use super::{rom_offset, Mapper};
+
+// 512 half-bytes of RAM built into the MBC2 chip itself
+const RAM_SIZE: usize = 0x200;
+
+#[derive(Clone)]
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        self.rom[rom_offset(&self.rom, bank, address)]
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        // Both registers live in 0x0000-0x3FFF, address bit 8 picks which one
+        match address {
+            0x0000..=0x3FFF if address & 0x0100 == 0 => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0000..=0x3FFF => self.rom_bank = (value & 0x0F).max(1),
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // Only the lower nibble exists, the upper one floats high.
+        // The 512 entries echo across the whole 0xA000-0xBFFF window
+        0xF0 | self.ram[address as usize & (RAM_SIZE - 1)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[address as usize & (RAM_SIZE - 1)] = value & 0x0F;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn reset(&mut self) {
+        self.ram_enabled = false;
+        self.rom_bank = 1;
+    }
+}
@@ -0,0 +1,104 @@
+use super::{ram_offset, rom_offset, Mapper, Rtc};
+
+#[derive(Clone)]
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    // 0x00-0x03 selects a RAM bank, 0x08-0x0C an RTC register
+    ram_select: u8,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rtc: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            rtc: has_rtc.then(Rtc::new),
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+        }
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        self.rom[rom_offset(&self.rom, bank, address)]
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_select = value,
+            0x6000..=0x7FFF => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match (self.ram_select, &self.rtc) {
+            (0x00..=0x03, _) if !self.ram.is_empty() => {
+                self.ram[ram_offset(&self.ram, self.ram_select as usize, address)]
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_select),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        match (self.ram_select, &mut self.rtc) {
+            (0x00..=0x03, _) if !self.ram.is_empty() => {
+                let offset = ram_offset(&self.ram, self.ram_select as usize, address);
+                self.ram[offset] = value;
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.write(self.ram_select, value),
+            _ => {},
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn reset(&mut self) {
+        self.ram_enabled = false;
+        self.rom_bank = 1;
+        self.ram_select = 0;
+    }
+
+    fn step(&mut self, cycles: i32) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.step(cycles);
+        }
+    }
+
+    fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+}
@@ -0,0 +1,102 @@
+use super::{ram_offset, rom_offset, Mapper, RumbleEvent};
+
+// Bit 3 of the RAM bank register drives the motor on rumble carts
+const RUMBLE_MOTOR: u8 = 0b0000_1000;
+
+#[derive(Clone)]
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    has_rumble: bool,
+    ram_enabled: bool,
+    // 9-bit ROM bank number. Unlike MBC1/MBC3, bank 0 can be mapped at 0x4000
+    rom_bank: u16,
+    ram_bank: u8,
+    motor_on: bool,
+    rumble_event: Option<RumbleEvent>,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            has_rumble,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            motor_on: false,
+            rumble_event: None,
+        }
+    }
+
+    fn write_ram_bank(&mut self, value: u8) {
+        if !self.has_rumble {
+            self.ram_bank = value & 0x0F;
+            return;
+        }
+        self.ram_bank = value & 0x07;
+        let motor_on = value & RUMBLE_MOTOR != 0;
+        if motor_on != self.motor_on {
+            self.motor_on = motor_on;
+            self.rumble_event = Some(if motor_on { RumbleEvent::Start } else { RumbleEvent::Stop });
+        }
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize,
+        };
+        self.rom[rom_offset(&self.rom, bank, address)]
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0x01) << 8),
+            0x4000..=0x5FFF => self.write_ram_bank(value),
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[ram_offset(&self.ram, self.ram_bank as usize, address)]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset = ram_offset(&self.ram, self.ram_bank as usize, address);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn reset(&mut self) {
+        self.ram_enabled = false;
+        self.rom_bank = 1;
+        // Clearing the RAM bank register also stops the motor
+        self.write_ram_bank(0);
+    }
+
+    fn poll_rumble(&mut self) -> Option<RumbleEvent> {
+        self.rumble_event.take()
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The RTC oscillator runs at 32768 Hz, which divides the 4 MiHz system clock evenly
+const CYCLES_PER_SECOND: u32 = 4_194_304;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Size of the RTC footer appended to .sav files, in the layout shared by
+// BGB and VBA: 5 current and 5 latched registers as u32 LE, then a u64 LE
+// UNIX timestamp of when the save was written
+pub const SAVE_SIZE: usize = 48;
+
+const SECONDS: usize = 0;
+const MINUTES: usize = 1;
+const HOURS: usize = 2;
+const DAYS_LOW: usize = 3;
+const DAYS_HIGH: usize = 4;
+
+const DAYS_HIGH_BIT8: u8 = 0b0000_0001;
+const DAYS_HIGH_HALT: u8 = 0b0100_0000;
+const DAYS_HIGH_CARRY: u8 = 0b1000_0000;
+
+// MBC3 real time clock. Registers are indexed by RTC select value - 0x08:
+// seconds, minutes, hours, day counter low byte and day counter high/flags
+#[derive(Clone, Default)]
+pub struct Rtc {
+    registers: [u8; 5],
+    latched: [u8; 5],
+    cycles: u32,
+    latch_armed: bool,
+    // When the clock was last persisted, as seconds since the UNIX epoch
+    saved_at: Option<u64>,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(&mut self, cycles: i32) {
+        if self.registers[DAYS_HIGH] & DAYS_HIGH_HALT != 0 {
+            return;
+        }
+        self.cycles += cycles as u32;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.tick_second();
+        }
+    }
+
+    fn tick_second(&mut self) {
+        // Out of range values written by the game keep counting up to the
+        // register width and then wrap to 0 without carrying
+        self.registers[SECONDS] = (self.registers[SECONDS] + 1) & 0x3F;
+        if self.registers[SECONDS] != 60 {
+            return;
+        }
+        self.registers[SECONDS] = 0;
+        self.registers[MINUTES] = (self.registers[MINUTES] + 1) & 0x3F;
+        if self.registers[MINUTES] != 60 {
+            return;
+        }
+        self.registers[MINUTES] = 0;
+        self.registers[HOURS] = (self.registers[HOURS] + 1) & 0x1F;
+        if self.registers[HOURS] != 24 {
+            return;
+        }
+        self.registers[HOURS] = 0;
+        let days = self.days() + 1;
+        if days > 0x1FF {
+            self.registers[DAYS_HIGH] |= DAYS_HIGH_CARRY;
+        }
+        self.set_days(days & 0x1FF);
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        if self.registers[DAYS_HIGH] & DAYS_HIGH_HALT != 0 {
+            return;
+        }
+        // Whole days go straight into the day counter
+        let days = self.days() as u64 + seconds / SECONDS_PER_DAY;
+        if days > 0x1FF {
+            self.registers[DAYS_HIGH] |= DAYS_HIGH_CARRY;
+        }
+        self.set_days((days & 0x1FF) as u16);
+        for _ in 0..seconds % SECONDS_PER_DAY {
+            self.tick_second();
+        }
+    }
+
+    fn days(&self) -> u16 {
+        (((self.registers[DAYS_HIGH] & DAYS_HIGH_BIT8) as u16) << 8) | self.registers[DAYS_LOW] as u16
+    }
+
+    fn set_days(&mut self, days: u16) {
+        self.registers[DAYS_LOW] = days as u8;
+        self.registers[DAYS_HIGH] = (self.registers[DAYS_HIGH] & !DAYS_HIGH_BIT8) | (days >> 8) as u8;
+    }
+
+    // Writing 0x00 then 0x01 to 0x6000-0x7FFF copies the running clock into
+    // the latched registers the game reads from
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers;
+        }
+        self.latch_armed = value == 0x00;
+    }
+
+    pub fn read(&self, select: u8) -> u8 {
+        self.latched[(select - 0x08) as usize]
+    }
+
+    pub fn write(&mut self, select: u8, value: u8) {
+        let index = (select - 0x08) as usize;
+        self.registers[index] = match index {
+            SECONDS => {
+                // Writing the seconds register resets the sub-second divider
+                self.cycles = 0;
+                value & 0x3F
+            },
+            MINUTES => value & 0x3F,
+            HOURS => value & 0x1F,
+            DAYS_LOW => value,
+            _ => value & (DAYS_HIGH_BIT8 | DAYS_HIGH_HALT | DAYS_HIGH_CARRY),
+        };
+    }
+
+    // Catches the clock up with the wall-clock time that passed since it was
+    // saved. Without a save, sets it to the host's time of day (UTC), keeping
+    // the day counter the game has been using
+    pub fn sync_to_host(&mut self) {
+        let now = unix_now();
+        match self.saved_at {
+            Some(saved_at) => self.advance(now.saturating_sub(saved_at)),
+            None => {
+                self.registers[SECONDS] = (now % 60) as u8;
+                self.registers[MINUTES] = (now / 60 % 60) as u8;
+                self.registers[HOURS] = (now / 3600 % 24) as u8;
+            },
+        }
+        self.cycles = 0;
+        self.latched = self.registers;
+    }
+
+    pub fn save(&mut self) -> [u8; SAVE_SIZE] {
+        let now = unix_now();
+        self.saved_at = Some(now);
+        let mut bytes = [0; SAVE_SIZE];
+        for (i, &value) in self.registers.iter().chain(self.latched.iter()).enumerate() {
+            bytes[i * 4] = value;
+        }
+        bytes[40..].copy_from_slice(&now.to_le_bytes());
+        bytes
+    }
+
+    pub fn load(&mut self, bytes: &[u8; SAVE_SIZE]) {
+        // Saves from other emulators can hold bits the registers don't have
+        const MASKS: [u8; 5] = [0x3F, 0x3F, 0x1F, 0xFF, DAYS_HIGH_BIT8 | DAYS_HIGH_HALT | DAYS_HIGH_CARRY];
+        for i in 0..5 {
+            self.registers[i] = bytes[i * 4] & MASKS[i];
+            self.latched[i] = bytes[20 + i * 4] & MASKS[i];
+        }
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&bytes[40..]);
+        self.saved_at = Some(u64::from_le_bytes(timestamp));
+        self.cycles = 0;
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
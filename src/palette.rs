@@ -0,0 +1,61 @@
+use crate::gpu::{Color, Layer, Pixel};
+
+// 0xRRGGBB for each shade, lightest first
+pub type Colors = [u32; 4];
+
+const GREYSCALE: Colors = [0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000];
+const GREEN: Colors = [0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F];
+const POCKET: Colors = [0xC4CFA1, 0x8B956D, 0x4D533C, 0x1F1F1F];
+const LIGHT: Colors = [0x00B581, 0x009A71, 0x00694A, 0x004F3B];
+
+// Maps DMG shades to screen colours. Like the CGB does for DMG games, the
+// background and each sprite palette can have their own colours
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub bg: Colors,
+    pub obj0: Colors,
+    pub obj1: Colors,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::uniform(GREYSCALE)
+    }
+}
+
+impl Palette {
+    pub fn uniform(colors: Colors) -> Self {
+        Self {
+            bg: colors,
+            obj0: colors,
+            obj1: colors,
+        }
+    }
+
+    // "grey" is plain greyscale, "green" the original DMG screen, "pocket" the
+    // Game Boy Pocket and "light" the backlit Game Boy Light
+    pub fn preset(name: &str) -> Option<Self> {
+        let colors = match name.to_ascii_lowercase().as_str() {
+            "grey" | "gray" => GREYSCALE,
+            "green" => GREEN,
+            "pocket" => POCKET,
+            "light" => LIGHT,
+            _ => return None,
+        };
+        Some(Self::uniform(colors))
+    }
+
+    pub fn rgb(&self, pixel: Pixel) -> u32 {
+        let colors = match pixel.layer {
+            Layer::Bg => &self.bg,
+            Layer::Obj0 => &self.obj0,
+            Layer::Obj1 => &self.obj1,
+        };
+        match pixel.color {
+            Color::White => colors[0],
+            Color::LGray => colors[1],
+            Color::DGray => colors[2],
+            Color::Black => colors[3],
+        }
+    }
+}
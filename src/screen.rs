@@ -1,37 +1,95 @@
-use minifb::{Key, Window, WindowOptions};
-use crate::gpu::{Tile, Color};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use crate::config::{Action, Config, Hotkey};
+use crate::gpu::{Pixel, SCREEN_WIDTH as WIDTH, SCREEN_HEIGHT as HEIGHT};
+use crate::joypad::Button;
+use crate::palette::Palette;
 include!("gameboy_logo_buffer.rs");
 
+pub struct Screen {
+    window: Window,
+    buffer: Vec<u32>,
+    bindings: Vec<(Key, Action)>,
+    palette: Palette,
+}
+
+impl Screen {
+    pub fn new(config: Config) -> Self {
+        let mut window = Window::new(
+            "Rustboy",
+            WIDTH,
+            HEIGHT,
+            WindowOptions::default(),
+        ).unwrap_or_else(|e| panic!("{}", e));
+        window.set_target_fps(60);
+
+        Self {
+            window,
+            buffer: vec![0; WIDTH * HEIGHT],
+            bindings: config.bindings,
+            palette: config.palette,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+
+    pub fn pressed_buttons(&self) -> Vec<Button> {
+        self.bindings
+            .iter()
+            .filter(|(key, _)| self.window.is_key_down(*key))
+            .filter_map(|&(_, action)| match action {
+                Action::Button(button) => Some(button),
+                Action::Hotkey(_) => None,
+            })
+            .collect()
+    }
+
+    // Hotkeys whose key went down since the last update
+    pub fn triggered_hotkeys(&self) -> Vec<Hotkey> {
+        self.bindings
+            .iter()
+            .filter(|(key, _)| self.window.is_key_pressed(*key, KeyRepeat::No))
+            .filter_map(|&(_, action)| match action {
+                Action::Hotkey(hotkey) => Some(hotkey),
+                Action::Button(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn hotkey_held(&self, hotkey: Hotkey) -> bool {
+        self.bindings
+            .iter()
+            .any(|&(key, action)| action == Action::Hotkey(hotkey) && self.window.is_key_down(key))
+    }
+
+    // Writes the last rendered frame as a binary PPM
+    pub fn screenshot(&self, path: &Path) -> io::Result<()> {
+        let mut data = format!("P6\n{} {}\n255\n", WIDTH, HEIGHT).into_bytes();
+        for &pixel in &self.buffer {
+            data.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+        }
+        fs::write(path, data)
+    }
+
+    // Keeps the window responsive without drawing a new frame
+    pub fn refresh(&mut self) {
+        self.window.update_with_buffer(&self.buffer, WIDTH, HEIGHT).unwrap();
+    }
 
-const WIDTH: usize = 160;
-const HEIGHT: usize = 144;
-
-pub fn render(fbuf: &[Tile]) {
-    let mut window = Window::new(
-        "Rustboy",
-        WIDTH,
-        HEIGHT,
-        WindowOptions::default(),
-    ).unwrap_or_else(|e| panic!("{}", e));
-
-    let mut buffer = vec![0; WIDTH * HEIGHT];
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        for (i, tile) in fbuf.iter().enumerate() {
-            for (j, c) in tile.pixels.iter().enumerate() {
-                buffer[i*64 + j] = match c {
-                    Color::White => 0x00,
-                    Color::LGray => 0x55,
-                    Color::DGray => 0xAA,
-                    Color::Black => 0xFF,
-                } 
-            }
+    pub fn render(&mut self, frame: &[Pixel]) {
+        for (rgb, &pixel) in self.buffer.iter_mut().zip(frame) {
+            *rgb = self.palette.rgb(pixel);
         }
         // for (i, &grey) in GAMEBOY_LOGO_SCREEN.iter().enumerate() {
         //     let rgb = (grey as u32) * 0x010101;
-        //     buffer[i] = rgb;
-        // } 
+        //     self.buffer[i] = rgb;
+        // }
 
-        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+        self.window.update_with_buffer(&self.buffer, WIDTH, HEIGHT).unwrap();
     }
-     
 }
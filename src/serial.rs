@@ -0,0 +1,70 @@
+use crate::interrupts::{Interrupt, Interrupts};
+use crate::MemoryAddressError;
+
+// The internal clock shifts one bit every 512 cycles (8192 Hz)
+const CYCLES_PER_BIT: i32 = 512;
+
+const SC_TRANSFER: u8 = 0b1000_0000;
+const SC_INTERNAL_CLOCK: u8 = 0b0000_0001;
+
+// SB (0xFF01) and SC (0xFF02). No link partner is ever connected, so
+// transfers driven by our own clock shift in 1s and externally clocked
+// transfers never finish
+#[derive(Clone)]
+pub struct Serial {
+    data: u8,
+    control: u8,
+    bits_remaining: u8,
+    cycles: i32,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Self {
+            data: 0,
+            control: 0,
+            bits_remaining: 0,
+            cycles: 0,
+        }
+    }
+
+    pub fn step(&mut self, cycles: i32, interrupts: &mut Interrupts) {
+        if self.bits_remaining == 0 {
+            return;
+        }
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_BIT && self.bits_remaining > 0 {
+            self.cycles -= CYCLES_PER_BIT;
+            self.data = (self.data << 1) | 1;
+            self.bits_remaining -= 1;
+            if self.bits_remaining == 0 {
+                self.control &= !SC_TRANSFER;
+                interrupts.request(Interrupt::Serial);
+            }
+        }
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        Ok(match address {
+            0xFF01 => self.data,
+            0xFF02 => 0x7E | self.control,
+            _ => return Err(MemoryAddressError),
+        })
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            0xFF01 => self.data = value,
+            0xFF02 => {
+                self.control = value & (SC_TRANSFER | SC_INTERNAL_CLOCK);
+                let internal = SC_TRANSFER | SC_INTERNAL_CLOCK;
+                if self.control & internal == internal {
+                    self.bits_remaining = 8;
+                    self.cycles = 0;
+                }
+            },
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::interrupts::{Interrupt, Interrupts};
+use crate::MemoryAddressError;
+
+const TAC_ENABLE: u8 = 0b0000_0100;
+
+// DIV (0xFF04), TIMA (0xFF05), TMA (0xFF06) and TAC (0xFF07).
+// TIMA isn't clocked directly: it increments whenever the divider bit chosen
+// by TAC (ANDed with the enable bit) goes from 1 to 0. That's why writing DIV
+// or TAC can increment TIMA too
+#[derive(Clone)]
+pub struct Timer {
+    // DIV is the upper byte of this internal counter
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA overflowed during the last M-cycle and reads as 0x00. TMA gets
+    // loaded and the interrupt requested one M-cycle later
+    overflow_pending: bool,
+    // TIMA was reloaded from TMA during the last M-cycle
+    reloading: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            divider: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+            reloading: false,
+        }
+    }
+
+    pub fn step(&mut self, cycles: i32, interrupts: &mut Interrupts) {
+        for _ in 0..cycles / 4 {
+            self.tick(interrupts);
+        }
+    }
+
+    // Advances the timer by one M-cycle
+    fn tick(&mut self, interrupts: &mut Interrupts) {
+        self.reloading = false;
+        if self.overflow_pending {
+            self.overflow_pending = false;
+            self.tima = self.tma;
+            self.reloading = true;
+            interrupts.request(Interrupt::Timer);
+        }
+        let signal = self.signal();
+        self.divider = self.divider.wrapping_add(4);
+        self.detect_falling_edge(signal);
+    }
+
+    fn signal(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _ => 7,
+        };
+        self.tac & TAC_ENABLE != 0 && self.divider & (1 << bit) != 0
+    }
+
+    fn detect_falling_edge(&mut self, old_signal: bool) {
+        if old_signal && !self.signal() {
+            let (tima, overflow) = self.tima.overflowing_add(1);
+            self.tima = tima;
+            self.overflow_pending |= overflow;
+        }
+    }
+
+    pub fn reset_divider(&mut self) {
+        let signal = self.signal();
+        self.divider = 0;
+        self.detect_falling_edge(signal);
+    }
+
+    pub fn read(&self, address: u16) -> Result<u8, MemoryAddressError> {
+        Ok(match address {
+            0xFF04 => (self.divider >> 8) as u8,
+            0xFF05 => self.tima,
+            0xFF06 => self.tma,
+            0xFF07 => 0xF8 | self.tac,
+            _ => return Err(MemoryAddressError),
+        })
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), MemoryAddressError> {
+        match address {
+            // Any write resets the whole divider
+            0xFF04 => self.reset_divider(),
+            0xFF05 => {
+                // The reload from TMA wins over writes in the same M-cycle,
+                // while writing during the overflow delay cancels the reload
+                if !self.reloading {
+                    self.tima = value;
+                    self.overflow_pending = false;
+                }
+            },
+            0xFF06 => {
+                self.tma = value;
+                if self.reloading {
+                    self.tima = value;
+                }
+            },
+            0xFF07 => {
+                let signal = self.signal();
+                self.tac = value & 0x07;
+                self.detect_falling_edge(signal);
+            },
+            _ => return Err(MemoryAddressError),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Enabled and clocked from divider bit 3, so TIMA counts every 16 cycles
+    fn fast_timer() -> Timer {
+        let mut timer = Timer::new();
+        timer.write(0xFF07, 0b101).unwrap();
+        timer
+    }
+
+    // Runs until TIMA has just overflowed and the reload is pending
+    fn overflow(timer: &mut Timer, interrupts: &mut Interrupts) {
+        timer.write(0xFF06, 0x80).unwrap();
+        timer.write(0xFF05, 0xFF).unwrap();
+        timer.step(16, interrupts);
+    }
+
+    #[test]
+    fn div_write_on_high_bit_increments_tima() {
+        let mut timer = fast_timer();
+        let mut interrupts = Interrupts::new();
+        timer.step(8, &mut interrupts);
+        timer.write(0xFF04, 0x12).unwrap();
+        assert_eq!(timer.read(0xFF04).unwrap(), 0);
+        assert_eq!(timer.read(0xFF05).unwrap(), 1);
+    }
+
+    #[test]
+    fn tac_change_on_high_bit_increments_tima() {
+        let mut timer = fast_timer();
+        let mut interrupts = Interrupts::new();
+        timer.step(8, &mut interrupts);
+        // Bit 9 is still clear, so the selected signal drops
+        timer.write(0xFF07, 0b100).unwrap();
+        assert_eq!(timer.read(0xFF05).unwrap(), 1);
+    }
+
+    #[test]
+    fn tima_reads_zero_until_reload() {
+        let mut timer = fast_timer();
+        let mut interrupts = Interrupts::new();
+        overflow(&mut timer, &mut interrupts);
+        assert_eq!(timer.read(0xFF05).unwrap(), 0);
+        assert_eq!(interrupts.flag, 0);
+        timer.step(4, &mut interrupts);
+        assert_eq!(timer.read(0xFF05).unwrap(), 0x80);
+        assert_eq!(interrupts.flag, Interrupt::Timer.bit());
+    }
+
+    #[test]
+    fn tima_write_during_delay_cancels_reload() {
+        let mut timer = fast_timer();
+        let mut interrupts = Interrupts::new();
+        overflow(&mut timer, &mut interrupts);
+        timer.write(0xFF05, 0x42).unwrap();
+        timer.step(4, &mut interrupts);
+        assert_eq!(timer.read(0xFF05).unwrap(), 0x42);
+        assert_eq!(interrupts.flag, 0);
+    }
+}